@@ -1,5 +1,5 @@
 use regex::Regex;
-use std::collections::HashMap;
+use serde::Deserialize;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::fs::File;
@@ -12,6 +12,53 @@ fn read_input() -> Vec<String> {
         .collect::<Vec<String>>()
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Check {
+    Count,
+    Positions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Indexing {
+    #[default]
+    Chars,
+    Bytes,
+}
+
+fn default_case_sensitive() -> bool { true }
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Policy {
+    name: String,
+    check: Check,
+    low: Option<usize>,
+    high: Option<usize>,
+    #[serde(default = "default_case_sensitive")]
+    case_sensitive: bool,
+    #[serde(default)]
+    indexing: Indexing,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    policy: Vec<Policy>,
+}
+
+// Unknown keys are rejected so a misspelled option isn't silently ignored
+fn read_policies(path: &str) -> Result<Vec<Policy>, String> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut contents))
+        .map_err(|e| format!("{}: {}", path, e))?;
+    toml::from_str::<PolicyFile>(&contents)
+        .map(|f| f.policy)
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
 struct ParsedLine {
     c: char,
     low: usize,
@@ -29,41 +76,52 @@ fn parse_line(line: &str) -> ParsedLine {
         password: caps.get(4).unwrap().as_str().to_string()}
 }
 
-fn is_valid_part1(line: &str) -> bool {
-    let parsed_line = parse_line(line);
-    let char_map = parsed_line.password
-        .chars()
-        .fold(HashMap::new(), |mut acc, c| {
-            *acc.entry(c).or_insert(0) += 1;
-            acc
-        });
-
-    let count = *(char_map.get(&parsed_line.c).unwrap_or(&0));
-    count >= parsed_line.low && count <= parsed_line.high
-}
-
-fn is_valid_part2(line: &str) -> bool {
-    let parsed_line = parse_line(line);
-    let c1 = parsed_line.password.chars().nth(parsed_line.low-1).map_or(false, |c| c==parsed_line.c);
-    let c2 = parsed_line.password.chars().nth(parsed_line.high-1).map_or(false, |c| c==parsed_line.c);
+impl Policy {
+    fn matches(&self, a: char, b: char) -> bool {
+        if self.case_sensitive {
+            a == b
+        } else {
+            a.to_lowercase().eq(b.to_lowercase())
+        }
+    }
 
-    c1 ^ c2
-}
+    // Positions are 1-based; position 0 or one past the end never matches
+    fn char_at(&self, password: &str, pos: usize) -> Option<char> {
+        let idx = pos.checked_sub(1)?;
+        match self.indexing {
+            Indexing::Chars => password.chars().nth(idx),
+            Indexing::Bytes => password.get(idx..).and_then(|s| s.chars().next()),
+        }
+    }
 
-fn part_1(input: &Vec<String>) {
-    let valid_count = input.iter()
-        .filter(|x| is_valid_part1(x))
-        .count();
+    fn is_valid(&self, line: &str) -> bool {
+        let parsed_line = parse_line(line);
+        let low = self.low.unwrap_or(parsed_line.low);
+        let high = self.high.unwrap_or(parsed_line.high);
 
-    println!("Valid passwords count: {}", valid_count);
+        match self.check {
+            Check::Count => {
+                let count = parsed_line.password
+                    .chars()
+                    .filter(|c| self.matches(*c, parsed_line.c))
+                    .count();
+                (low..=high).contains(&count)
+            },
+            Check::Positions => {
+                let c1 = self.char_at(&parsed_line.password, low).is_some_and(|c| self.matches(c, parsed_line.c));
+                let c2 = self.char_at(&parsed_line.password, high).is_some_and(|c| self.matches(c, parsed_line.c));
+                c1 ^ c2
+            },
+        }
+    }
 }
 
-fn part_2(input: &Vec<String>) {
+fn apply_policy(input: &[String], policy: &Policy) {
     let valid_count = input.iter()
-        .filter(|x| is_valid_part2(x))
+        .filter(|x| policy.is_valid(x))
         .count();
 
-    println!("Valid passwords count: {}", valid_count);
+    println!("{}: Valid passwords count: {}", policy.name, valid_count);
 }
 
 fn main() {
@@ -71,6 +129,15 @@ fn main() {
     let input = read_input();
     println!("Input read");
 
-    part_1(&input);
-    part_2(&input);
+    let policies_path = std::env::args().nth(1).unwrap_or_else(|| "src/policies.toml".to_string());
+    let policies = match read_policies(&policies_path) {
+        Ok(policies) => policies,
+        Err(e) => {
+            println!("Failed to load policies: {}", e);
+            return;
+        }
+    };
+    for policy in policies {
+        apply_policy(&input, &policy);
+    }
 }
@@ -1,16 +1,77 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::fs::File;
+use std::fmt;
+use std::ops::RangeInclusive;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 enum Cell {
     Clear,
-    Tree
+    Tree,
+    Snow,
+    Ice,
+    Rock,
+}
+
+impl Cell {
+    fn new(c: char) -> Option<Cell> {
+        match c {
+            '.' => Some(Cell::Clear),
+            '#' => Some(Cell::Tree),
+            '*' => Some(Cell::Snow),
+            '~' => Some(Cell::Ice),
+            '^' => Some(Cell::Rock),
+            _ => None,
+        }
+    }
+
+    fn symbol(&self) -> char {
+        match self {
+            Cell::Clear => '.',
+            Cell::Tree => '#',
+            Cell::Snow => '*',
+            Cell::Ice => '~',
+            Cell::Rock => '^',
+        }
+    }
+}
+
+// Line and column numbers are 1-based
+#[derive(Debug)]
+enum MapError {
+    Empty,
+    UnknownSymbol { line: usize, column: usize, symbol: char },
+    EmptyRow { line: usize },
+    RowLength { line: usize, expected: usize, found: usize },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Empty => write!(f, "map has no rows"),
+            MapError::UnknownSymbol { line, column, symbol } =>
+                write!(f, "line {}, column {}: unknown symbol {:?}", line, column, symbol),
+            MapError::EmptyRow { line } => write!(f, "line {}: empty row", line),
+            MapError::RowLength { line, expected, found } =>
+                write!(f, "line {}: row has {} cells, expected {}", line, found, expected),
+        }
+    }
+}
+
+// Whether every row must be as wide as the first one, or each row wraps at its own width
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum RowWidths {
+    Uniform,
+    PerRow,
 }
 
 #[derive(Debug)]
 struct Map {
     data: Vec<Vec<Cell>>,
+    // Width of the widest row
     width: usize,
     height: usize,
 }
@@ -18,63 +79,428 @@ struct Map {
 impl Map {
     fn new(data: Vec<Vec<Cell>>) -> Map {
         let height = data.len();
-        let width = data[0].len();
+        let width = data.iter().map(|row| row.len()).max().unwrap_or(0);
         Map {data, width, height}
     }
 
+    fn parse(lines: &[String], row_widths: RowWidths) -> Result<Map, Vec<MapError>> {
+        if lines.is_empty() {
+            return Err(vec![MapError::Empty]);
+        }
+
+        let mut errors = vec![];
+        let expected = lines[0].chars().count();
+        let data = lines.iter()
+            .enumerate()
+            .map(|(i, l)| {
+                let found = l.chars().count();
+                if found == 0 {
+                    errors.push(MapError::EmptyRow { line: i + 1 });
+                } else if row_widths == RowWidths::Uniform && found != expected {
+                    errors.push(MapError::RowLength { line: i + 1, expected, found });
+                }
+
+                l.chars()
+                    .enumerate()
+                    .filter_map(|(j, c)| {
+                        let cell = Cell::new(c);
+                        if cell.is_none() {
+                            errors.push(MapError::UnknownSymbol { line: i + 1, column: j + 1, symbol: c });
+                        }
+                        cell
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        if errors.is_empty() { Ok(Map::new(data)) } else { Err(errors) }
+    }
+
+    fn row_width(&self, y: usize) -> usize {
+        self.data[y].len()
+    }
+
     fn get(&self, x: usize, y: usize) -> &Cell {
-        let real_x = x % self.width;
+        let real_x = x % self.row_width(y);
         &self.data[y][real_x]
     }
 }
 
-fn read_input() -> Map {
+fn read_input(row_widths: RowWidths) -> Result<Map, Vec<MapError>> {
     let input_file = File::open("src/input.txt").unwrap();
     let reader = BufReader::new(input_file);
     let input = reader.lines()
         .map(|x|x.unwrap())
-        .map(|l| l.chars()
-                   .map(|c| if c == '.' { Cell::Clear } else { Cell::Tree })
-                   .collect::<Vec<_>>())
         .collect::<Vec<_>>();
 
-    Map::new(input)
+    Map::parse(&input, row_widths)
 }
 
-fn check_slope(map: &Map, slope: (usize, usize)) -> usize {
-    let mut pos = (0,0);
+// Each step of the walk moves `down` rows and `right` columns, so (3, 1) visits
+// rows 0, 3, 6... and (2, 2) visits rows 0, 2, 4... Negative `right` walks
+// leftwards.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct Slope {
+    down: usize,
+    right: isize,
+}
+
+impl Slope {
+    fn new(down: usize, right: isize) -> Slope {
+        assert!(down > 0, "slope must move down");
+        Slope { down, right }
+    }
+}
+
+// (y, x) positions visited by the slope, x not wrapped to the map width
+fn slope_positions(map: &Map, slope: Slope) -> Vec<(usize, isize)> {
+    let mut pos = (0, 0isize);
 
-    let mut trees = 0;
+    let mut positions = vec![];
     while pos.0 < map.height {
-        if *map.get(pos.1, pos.0) == Cell::Tree { trees += 1; }
+        positions.push(pos);
 
-        pos.0 += slope.0;
-        pos.1 += slope.1;
+        pos.0 += slope.down;
+        pos.1 += slope.right;
     }
 
-    trees
+    positions
+}
+
+fn wrap_x(map: &Map, y: usize, x: isize) -> usize {
+    x.rem_euclid(map.row_width(y) as isize) as usize
+}
+
+fn check_slope(map: &Map, slope: Slope) -> usize {
+    slope_positions(map, slope).iter()
+        .filter(|(y, x)| *map.get(wrap_x(map, *y, *x), *y) == Cell::Tree)
+        .count()
+}
+
+#[derive(Debug)]
+struct SearchResult {
+    min: (usize, Vec<Slope>),
+    max: (usize, Vec<Slope>),
+}
+
+// Evaluates every slope with `right` and `down` in the given ranges
+fn search_slopes(map: &Map,
+                 rights: RangeInclusive<isize>,
+                 downs: RangeInclusive<usize>) -> Option<SearchResult> {
+    let results = downs
+        .filter(|down| *down > 0)
+        .flat_map(|down| rights.clone().map(move |right| (down, right)))
+        .map(|(down, right)| {
+            let slope = Slope::new(down, right);
+            (check_slope(map, slope), slope)
+        })
+        .collect::<Vec<_>>();
+
+    let min = results.iter().map(|r| r.0).min()?;
+    let max = results.iter().map(|r| r.0).max()?;
+    let with_count = |n| results.iter()
+        .filter(|r| r.0 == n)
+        .map(|r| r.1)
+        .collect::<Vec<_>>();
+
+    Some(SearchResult { min: (min, with_count(min)), max: (max, with_count(max)) })
 }
 
 fn part1(map: &Map) {
-    let trees = check_slope(map, (1,3));
+    let trees = check_slope(map, Slope::new(1,3));
     println!("Trees: {}", trees);
 }
 
 fn part2(map: &Map) {
 
-    let t1 = check_slope(map, (1,1));
-    let t2 = check_slope(map, (1,3));
-    let t3 = check_slope(map, (1,5));
-    let t4 = check_slope(map, (1,7));
-    let t5 = check_slope(map, (2,1));
+    let t1 = check_slope(map, Slope::new(1,1));
+    let t2 = check_slope(map, Slope::new(1,3));
+    let t3 = check_slope(map, Slope::new(1,5));
+    let t4 = check_slope(map, Slope::new(1,7));
+    let t5 = check_slope(map, Slope::new(2,1));
 
     println!("{}, {}, {}, {}, {}", t1, t2, t3, t4, t5);
     println!("{}", t1 * t2 * t3 * t4 * t5);
 }
 
+fn search(map: &Map) {
+    match search_slopes(map, -10..=10, 1..=5) {
+        Some(res) => {
+            println!("Fewest trees: {} {:?}", res.min.0, res.min.1);
+            println!("Most trees: {} {:?}", res.max.0, res.max.1);
+        },
+        None => println!("No slopes to search"),
+    }
+}
+
+// Cost of entering each kind of cell; cells without a cost are impassable
+#[derive(Debug)]
+struct TerrainCosts {
+    costs: HashMap<Cell, usize>,
+}
+
+impl Default for TerrainCosts {
+    fn default() -> TerrainCosts {
+        let costs = vec![(Cell::Clear, 1), (Cell::Tree, 10), (Cell::Snow, 3), (Cell::Ice, 2)];
+        TerrainCosts { costs: costs.into_iter().collect() }
+    }
+}
+
+// Line numbers are 1-based
+#[derive(Debug)]
+enum CostError {
+    UnknownSymbol { line: usize, symbol: String },
+    MissingCost { line: usize },
+    BadCost { line: usize, cost: String },
+    ExtraText { line: usize, text: String },
+}
+
+impl fmt::Display for CostError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CostError::UnknownSymbol { line, symbol } => write!(f, "line {}: unknown symbol {:?}", line, symbol),
+            CostError::MissingCost { line } => write!(f, "line {}: missing cost", line),
+            CostError::BadCost { line, cost } =>
+                write!(f, "line {}: cost {:?} is neither a number nor x", line, cost),
+            CostError::ExtraText { line, text } => write!(f, "line {}: unexpected {:?} after the cost", line, text),
+        }
+    }
+}
+
+impl TerrainCosts {
+    // One `<symbol> <cost>` pair per line, `x` as the cost marks the terrain impassable
+    fn parse(lines: &[String]) -> Result<TerrainCosts, Vec<CostError>> {
+        let mut costs = HashMap::new();
+        let mut errors = vec![];
+        for (i, l) in lines.iter().enumerate() {
+            let line = i + 1;
+            let parts = l.split_whitespace().collect::<Vec<_>>();
+            let (symbol, cost) = match parts.as_slice() {
+                [] => continue,
+                [_] => {
+                    errors.push(CostError::MissingCost { line });
+                    continue;
+                },
+                [symbol, cost] => (*symbol, *cost),
+                [_, _, rest @ ..] => {
+                    errors.push(CostError::ExtraText { line, text: rest.join(" ") });
+                    continue;
+                },
+            };
+
+            let mut chars = symbol.chars();
+            let cell = match (chars.next().and_then(Cell::new), chars.next()) {
+                (Some(cell), None) => cell,
+                _ => {
+                    errors.push(CostError::UnknownSymbol { line, symbol: symbol.to_string() });
+                    continue;
+                },
+            };
+
+            if cost == "x" {
+                costs.remove(&cell);
+            } else {
+                match cost.parse::<usize>() {
+                    Ok(cost) => { costs.insert(cell, cost); },
+                    Err(_) => errors.push(CostError::BadCost { line, cost: cost.to_string() }),
+                }
+            }
+        }
+
+        if errors.is_empty() { Ok(TerrainCosts { costs }) } else { Err(errors) }
+    }
+
+    fn get(&self, cell: &Cell) -> Option<usize> {
+        self.costs.get(cell).cloned()
+    }
+}
+
+// Moves as (down, right) offsets; `right` wraps around the map like the slopes do
+const TOBOGGAN_MOVES: [(isize, isize); 3] = [(1, -1), (1, 0), (1, 1)];
+const ORTHOGONAL_MOVES: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+#[derive(Debug)]
+struct Route {
+    cost: usize,
+    // (y, x) positions with x in 0..width
+    path: Vec<(usize, usize)>,
+}
+
+// Dijkstra from any cell in the top row to any cell in the bottom row. The cost
+// of a route is the sum of the costs of every cell on it, including the first.
+fn cheapest_route(map: &Map, costs: &TerrainCosts, moves: &[(isize, isize)]) -> Option<Route> {
+    let mut dist = (0..map.height)
+        .map(|y| vec![usize::MAX; map.row_width(y)])
+        .collect::<Vec<_>>();
+    let mut prev: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+
+    for (x, d) in dist[0].iter_mut().enumerate() {
+        if let Some(cost) = costs.get(map.get(x, 0)) {
+            *d = cost;
+            queue.push(Reverse((cost, 0, x)));
+        }
+    }
+
+    while let Some(Reverse((cost, y, x))) = queue.pop() {
+        if cost > dist[y][x] { continue; }
+
+        if y == map.height - 1 {
+            let mut path = vec![(y, x)];
+            while let Some(p) = prev.get(path.last().unwrap()) {
+                path.push(*p);
+            }
+            path.reverse();
+            return Some(Route { cost, path });
+        }
+
+        for (dy, dx) in moves {
+            let ny = y as isize + dy;
+            if ny < 0 || ny >= map.height as isize { continue; }
+            let ny = ny as usize;
+            let nx = wrap_x(map, ny, x as isize + dx);
+
+            if let Some(step) = costs.get(map.get(nx, ny)) {
+                let next = cost + step;
+                if next < dist[ny][nx] {
+                    dist[ny][nx] = next;
+                    prev.insert((ny, nx), (y, x));
+                    queue.push(Reverse((next, ny, nx)));
+                }
+            }
+        }
+    }
+
+    None
+}
+
+// The default costs when there's no costs file
+fn read_costs() -> Result<TerrainCosts, Vec<CostError>> {
+    match File::open("src/costs.txt") {
+        Ok(f) => TerrainCosts::parse(&BufReader::new(f).lines().map(|x|x.unwrap()).collect::<Vec<_>>()),
+        Err(_) => Ok(TerrainCosts::default()),
+    }
+}
+
+fn routes(map: &Map) {
+    let costs = match read_costs() {
+        Ok(costs) => costs,
+        Err(errors) => {
+            for e in errors {
+                println!("src/costs.txt {}", e);
+            }
+            return;
+        }
+    };
+    for (name, moves) in [("toboggan", &TOBOGGAN_MOVES[..]), ("orthogonal", &ORTHOGONAL_MOVES[..])] {
+        match cheapest_route(map, &costs, moves) {
+            Some(route) => println!("Cheapest {} route: cost {}, {} cells, starting at {:?}",
+                                    name, route.cost, route.path.len(), route.path[0]),
+            None => println!("No {} route to the bottom", name),
+        }
+    }
+}
+
+// Repeats the map horizontally as many times as the route needs and marks every
+// visited cell with `X` when it's a tree and `O` otherwise
+fn render_route(map: &Map, slope: Slope) -> Vec<String> {
+    let positions = slope_positions(map, slope);
+    let width = map.width as isize;
+    let min_x = positions.iter().map(|p| p.1).min().unwrap_or(0);
+    let max_x = positions.iter().map(|p| p.1).max().unwrap_or(0);
+    let first_rep = min_x.div_euclid(width);
+    let reps = (max_x.div_euclid(width) - first_rep + 1) as usize;
+    let origin = first_rep * width;
+
+    let mut lines = (0..map.height)
+        .map(|y| (0..reps * map.width)
+             .map(|x| map.get(wrap_x(map, y, x as isize + origin), y).symbol())
+             .collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    for (y, x) in positions {
+        let hit = *map.get(wrap_x(map, y, x), y) == Cell::Tree;
+        lines[y][(x - origin) as usize] = if hit { 'X' } else { 'O' };
+    }
+
+    lines.into_iter().map(|l| l.into_iter().collect()).collect()
+}
+
+fn write_text(path: &str, lines: &[String]) -> std::io::Result<()> {
+    let mut f = File::create(path)?;
+    for l in lines {
+        writeln!(f, "{}", l)?;
+    }
+    Ok(())
+}
+
+fn symbol_color(c: char) -> [u8; 3] {
+    match c {
+        '.' => [255, 255, 255],
+        '#' => [34, 139, 34],
+        '*' => [220, 220, 240],
+        '~' => [150, 200, 255],
+        '^' => [120, 120, 120],
+        'X' => [220, 20, 20],
+        'O' => [20, 20, 220],
+        _ => [0, 0, 0],
+    }
+}
+
+// Binary PPM with every cell drawn as a `scale` x `scale` square
+fn write_image(path: &str, lines: &[String], scale: usize) -> std::io::Result<()> {
+    let height = lines.len();
+    let width = lines.first().map_or(0, |l| l.chars().count());
+
+    let mut f = std::io::BufWriter::new(File::create(path)?);
+    write!(f, "P6\n{} {}\n255\n", width * scale, height * scale)?;
+    for l in lines {
+        let row = l.chars()
+            .flat_map(|c| std::iter::repeat_n(symbol_color(c), scale))
+            .flatten()
+            .collect::<Vec<_>>();
+        for _ in 0..scale {
+            f.write_all(&row)?;
+        }
+    }
+    Ok(())
+}
+
+fn render(map: &Map) {
+    let slope = Slope::new(1, 3);
+    let lines = render_route(map, slope);
+    for (path, result) in [("target/route.txt", write_text("target/route.txt", &lines)),
+                           ("target/route.ppm", write_image("target/route.ppm", &lines, 4))] {
+        match result {
+            Ok(()) => println!("Rendered {:?} to {}", slope, path),
+            Err(e) => println!("Failed to write {}: {}", path, e),
+        }
+    }
+}
+
 fn main() {
     println!("Reading input");
-    let input = read_input();
+    // --irregular lets rows have different widths, --render writes the part 1
+    // route to target/route.txt and target/route.ppm
+    let row_widths = if std::env::args().any(|a| a == "--irregular") {
+        RowWidths::PerRow
+    } else {
+        RowWidths::Uniform
+    };
+    let input = match read_input(row_widths) {
+        Ok(map) => map,
+        Err(errors) => {
+            for e in errors {
+                println!("{}", e);
+            }
+            return;
+        }
+    };
     part1(&input);
     part2(&input);
+    search(&input);
+    routes(&input);
+    if std::env::args().any(|a| a == "--render") {
+        render(&input);
+    }
 }
@@ -0,0 +1,206 @@
+use crate::schema::Schema;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+pub type Record = HashMap<String, String>;
+
+// Schema keys in schema order, then any other keys present in the records, sorted
+pub fn columns(records: &[&Record], schema: &Schema) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut columns = schema.documents.iter()
+        .flat_map(|d| d.fields.iter().map(|f| f.key.clone()))
+        .filter(|k| seen.insert(k.clone()))
+        .collect::<Vec<_>>();
+
+    let mut extra = records.iter()
+        .flat_map(|r| r.keys())
+        .filter(|k| !seen.contains(*k))
+        .cloned()
+        .collect::<Vec<_>>();
+    extra.sort();
+    extra.dedup();
+
+    columns.extend(extra);
+    columns
+}
+
+// Empty values are quoted so they stay distinct from missing fields
+fn csv_escape(s: &str) -> String {
+    if s.is_empty() || s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+// Missing fields are written as empty cells
+pub fn to_csv(records: &[&Record], columns: &[String]) -> String {
+    let mut out = columns.iter().map(|c| csv_escape(c)).collect::<Vec<_>>().join(",");
+    out.push('\n');
+    for r in records {
+        let row = columns.iter()
+            .map(|c| r.get(c).map_or(String::new(), |v| csv_escape(v)))
+            .collect::<Vec<_>>()
+            .join(",");
+        out.push_str(&row);
+        out.push('\n');
+    }
+    out
+}
+
+// One JSON object per line, keys sorted
+pub fn to_json_lines(records: &[&Record]) -> String {
+    records.iter()
+        .map(|r| {
+            let sorted = r.iter().collect::<std::collections::BTreeMap<_, _>>();
+            serde_json::to_string(&sorted).unwrap() + "\n"
+        })
+        .collect()
+}
+
+fn end_cell(row: &mut Vec<Option<String>>, cell: &mut String, quoted: &mut bool) {
+    let value = std::mem::take(cell);
+    row.push(if value.is_empty() && !*quoted { None } else { Some(value) });
+    *quoted = false;
+}
+
+fn end_row(rows: &mut Vec<Vec<Option<String>>>, row: &mut Vec<Option<String>>) {
+    let row = std::mem::take(row);
+    if row.iter().any(|c| c.is_some()) {
+        rows.push(row);
+    }
+}
+
+// Splits CSV text into rows of cells, honoring quoted cells with embedded
+// commas, quotes and newlines. An unquoted empty cell is None. Rows of only
+// unquoted empty cells, like blank lines, are skipped.
+fn parse_csv_rows(contents: &str) -> Result<Vec<Vec<Option<String>>>, String> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut chars = contents.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        if c == '\n' { line += 1; }
+        match (in_quotes, c) {
+            (true, '"') if chars.peek() == Some(&'"') => { chars.next(); cell.push('"'); },
+            (true, '"') => in_quotes = false,
+            (true, _) => cell.push(c),
+            (false, '"') if cell.is_empty() && !quoted => { in_quotes = true; quoted = true; },
+            (false, '"') => return Err(format!("line {}: unexpected quote", line)),
+            (false, ',') => end_cell(&mut row, &mut cell, &mut quoted),
+            (false, '\r') => {},
+            (false, '\n') => {
+                end_cell(&mut row, &mut cell, &mut quoted);
+                end_row(&mut rows, &mut row);
+            },
+            (false, _) => cell.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("line {}: unterminated quoted cell", line));
+    }
+    if !cell.is_empty() || quoted || !row.is_empty() {
+        end_cell(&mut row, &mut cell, &mut quoted);
+        end_row(&mut rows, &mut row);
+    }
+    Ok(rows)
+}
+
+// Unquoted empty cells are missing fields, `""` is an empty value
+pub fn from_csv(contents: &str) -> Result<Vec<Record>, String> {
+    let rows = parse_csv_rows(contents)?;
+    let mut rows = rows.into_iter();
+    let header = rows.next().ok_or("missing header row")?
+        .into_iter()
+        .map(Option::unwrap_or_default)
+        .collect::<Vec<_>>();
+
+    rows.enumerate()
+        .map(|(i, row)| {
+            if row.len() != header.len() {
+                return Err(format!("row {}: {} cells, expected {}", i + 2, row.len(), header.len()));
+            }
+            Ok(header.iter().cloned()
+                .zip(row)
+                .filter_map(|(k, v)| v.map(|v| (k, v)))
+                .collect())
+        })
+        .collect()
+}
+
+pub fn from_json_lines(contents: &str) -> Result<Vec<Record>, String> {
+    contents.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| serde_json::from_str::<Record>(l).map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+// The batch format: `key:value` pairs separated by spaces, records separated by blank lines
+pub fn to_batch(records: &[&Record], columns: &[String]) -> Result<String, String> {
+    let entries = records.iter()
+        .enumerate()
+        .map(|(i, r)| {
+            columns.iter()
+                .filter_map(|c| r.get(c).map(|v| (c, v)))
+                .map(|(k, v)| {
+                    let unsafe_char = |c: char| c == ':' || c.is_whitespace();
+                    if k.is_empty() || k.contains(unsafe_char) || v.contains(unsafe_char) {
+                        Err(format!("record {}: {}:{} can't be written in the batch format", i + 1, k, v))
+                    } else {
+                        Ok(format!("{}:{}", k, v))
+                    }
+                })
+                .collect::<Result<Vec<_>, String>>()
+                .map(|pairs| pairs.join(" "))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(entries.join("\n\n") + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(pairs: &[(&str, &str)]) -> Record {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        let records = vec![
+            record(&[("byr", "1937"), ("hcl", "#fffffd")]),
+            record(&[("byr", ""), ("pid", "a,b")]),
+            record(&[("hcl", "say \"hi\""), ("pid", "two\nlines")]),
+        ];
+        let refs = records.iter().collect::<Vec<_>>();
+        let columns = vec!["byr".to_string(), "hcl".to_string(), "pid".to_string()];
+
+        assert_eq!(from_csv(&to_csv(&refs, &columns)), Ok(records));
+    }
+
+    #[test]
+    fn csv_keeps_empty_values_apart_from_missing_fields() {
+        let records = from_csv("byr,cid\n\"\",\n").unwrap();
+        assert_eq!(records, vec![record(&[("byr", "")])]);
+    }
+
+    #[test]
+    fn csv_skips_blank_lines() {
+        let records = from_csv("byr,cid\n1937,\n\n2002,147\n\n").unwrap();
+        assert_eq!(records, vec![record(&[("byr", "1937")]), record(&[("byr", "2002"), ("cid", "147")])]);
+    }
+
+    #[test]
+    fn json_lines_round_trip() {
+        let records = vec![record(&[("byr", ""), ("ecl", "amb")]), record(&[])];
+        let refs = records.iter().collect::<Vec<_>>();
+        assert_eq!(from_json_lines(&to_json_lines(&refs)), Ok(records));
+    }
+}
@@ -1,134 +1,272 @@
-use regex::Regex;
+mod convert;
+mod report;
+mod schema;
+
+use schema::DocumentType;
+use schema::Schema;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
 use std::io::prelude::*;
-use std::io::BufReader;
 use std::fs::File;
 
+// Invalid UTF-8 is replaced rather than rejected, and CRLF line endings are accepted
 fn read_input() -> Vec<String> {
-    let input_file = File::open("src/input.txt").unwrap();
-    let reader = BufReader::new(input_file);
-    reader.lines()
-        .map(|x|x.unwrap())
+    let mut bytes = vec![];
+    File::open("src/input.txt").unwrap().read_to_end(&mut bytes).unwrap();
+    String::from_utf8_lossy(&bytes)
+        .lines()
+        .map(|l| l.to_string())
         .collect::<Vec<_>>()
 }
 
-fn split_entries(lines: Vec<String>) -> Vec<String> {
-    lines.iter()
-        .fold(vec!["".to_string()], |mut acc, l| {
+#[derive(Debug)]
+struct Entry {
+    // 1-based, inclusive
+    first_line: usize,
+    last_line: usize,
+    lines: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Passport {
+    pub first_line: usize,
+    pub last_line: usize,
+    pub fields: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+enum WarningKind {
+    MalformedToken(String),
+    DuplicateField { key: String, first_line: usize, first_column: usize },
+    UnknownKey(String),
+}
+
+// Line and column are 1-based, columns count characters
+#[derive(Debug)]
+struct Warning {
+    line: usize,
+    column: usize,
+    kind: WarningKind,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            WarningKind::MalformedToken(token) =>
+                write!(f, "malformed token {:?}, expected key:value", token),
+            WarningKind::DuplicateField { key, first_line, first_column } =>
+                write!(f, "duplicate field {:?}, first seen at line {}, column {}; using the last value",
+                       key, first_line, first_column),
+            WarningKind::UnknownKey(key) => write!(f, "unknown key {:?}", key),
+        }
+    }
+}
+
+fn split_entries(lines: Vec<String>) -> Vec<Entry> {
+    lines.into_iter()
+        .enumerate()
+        .fold(Vec::<Entry>::new(), |mut acc, (i, l)| {
+            let line = i + 1;
             if l.trim().is_empty() {
-                acc.push("".to_string());
-            } else {
-                let len = acc.len();
-                acc[len-1] = acc[len-1].trim().to_string();
-                acc[len-1].push(' ');
-                acc[len-1].push_str(l);
+                return acc;
+            }
+
+            match acc.last_mut() {
+                Some(e) if e.last_line + 1 == line => {
+                    e.last_line = line;
+                    e.lines.push(l);
+                },
+                _ => acc.push(Entry { first_line: line, last_line: line, lines: vec![l] }),
             }
             acc
         })
 }
 
-fn parse_entries(entries: Vec<String>) -> Vec<HashMap<String, String>> {
-    entries.iter()
-        .map(|e| e.split(' ')
-            .fold(HashMap::new(), |mut acc, p| {
-                let kv = p.split(':').collect::<Vec<_>>();
-                if kv.len() != 2 {
-                    return acc;
-                }
+// (1-based column, token) for every whitespace separated token in the line
+fn tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (column, (i, c)) in line.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((column + 1, i)),
+            (true, Some((token_column, s))) => {
+                tokens.push((token_column, &line[s..i]));
+                start = None;
+            },
+            _ => {},
+        }
+    }
 
-                acc.insert(kv[0].to_string(), kv[1].to_string());
-                acc
-            }))
-        .collect::<Vec<HashMap<String, String>>>()
+    if let Some((token_column, s)) = start {
+        tokens.push((token_column, &line[s..]));
+    }
+    tokens
 }
 
-fn validate_usize(s: &str, low:usize, high:usize) -> bool {
-    let x = s.parse::<usize>().unwrap();
-    x >= low && x <= high
-}
+fn parse_entry(entry: Entry, known_keys: &HashSet<&str>, warnings: &mut Vec<Warning>) -> Passport {
+    let mut fields = HashMap::new();
+    let mut seen: HashMap<String, (usize, usize)> = HashMap::new();
 
-fn validate_height(s: &str) -> bool {
-    let re = Regex::new(r"^(\d+)(cm|in)$").unwrap();
-    let caps = re.captures(s);
-    if caps.is_none() { return false; }
-    let caps = caps.unwrap();
-    let num = caps.get(1).unwrap().as_str().parse::<usize>().unwrap();
-    let metric = caps.get(2).unwrap().as_str();
+    for (i, l) in entry.lines.iter().enumerate() {
+        let line = entry.first_line + i;
+        for (column, token) in tokens(l) {
+            let kv = token.split(':').collect::<Vec<_>>();
+            if kv.len() != 2 || kv[0].is_empty() {
+                warnings.push(Warning { line, column, kind: WarningKind::MalformedToken(token.to_string()) });
+                continue;
+            }
 
-    if metric == "in" {
-        num >= 59 && num <= 76
-    } else {
-        assert!(metric == "cm");
-        num >= 150 && num <= 193
+            let key = kv[0].to_string();
+            if !known_keys.contains(kv[0]) {
+                warnings.push(Warning { line, column, kind: WarningKind::UnknownKey(key.clone()) });
+            }
+
+            if let Some((first_line, first_column)) = seen.get(&key) {
+                warnings.push(Warning {
+                    line,
+                    column,
+                    kind: WarningKind::DuplicateField { key: key.clone(), first_line: *first_line, first_column: *first_column },
+                });
+            } else {
+                seen.insert(key.clone(), (line, column));
+            }
+
+            fields.insert(key, kv[1].to_string());
+        }
     }
-}
 
-fn validate_hair_color(s: &str) -> bool {
-    let re = Regex::new(r"^\s*#[0-9a-f]{6}\s*$").unwrap();
-    re.is_match(s)
+    Passport { first_line: entry.first_line, last_line: entry.last_line, fields }
 }
 
-fn validate_eye_color(s: &str) -> bool {
-    let re = Regex::new(r"^\s*(amb|blu|brn|gry|grn|hzl|oth)\s*$").unwrap();
-    re.is_match(s)
-}
+fn parse_entries(entries: Vec<Entry>, known_keys: &HashSet<&str>) -> (Vec<Passport>, Vec<Warning>) {
+    let mut warnings = vec![];
+    let passports = entries.into_iter()
+        .map(|e| parse_entry(e, known_keys, &mut warnings))
+        .collect::<Vec<_>>();
 
-fn validate_pid(s: &str) -> bool {
-    let re = Regex::new(r"^\s*\d{9}\s*$").unwrap();
-    re.is_match(s)
+    (passports, warnings)
 }
 
-fn part1(passports: &Vec<HashMap<String, String>>) {
+// `label` is "Valid" for the primary document and the document name otherwise
+fn part1(passports: &[Passport], document: &DocumentType, label: &str) {
     let valid = passports.iter()
-        .filter(|passport| {
-            passport.contains_key("byr") &&
-            passport.contains_key("iyr") &&
-            passport.contains_key("eyr") &&
-            passport.contains_key("hgt") &&
-            passport.contains_key("hcl") &&
-            passport.contains_key("ecl") &&
-            passport.contains_key("pid")
-
-        })
+        .filter(|passport| document.has_required_fields(&passport.fields))
         .count();
 
-    println!("Valid Part 1: {}", valid);
-}
-
-fn part2(passports: &Vec<HashMap<String, String>>) {
-    let have_fields = passports.iter()
-        .filter(|passport| {
-            passport.contains_key("byr") &&
-            passport.contains_key("iyr") &&
-            passport.contains_key("eyr") &&
-            passport.contains_key("hgt") &&
-            passport.contains_key("hcl") &&
-            passport.contains_key("ecl") &&
-            passport.contains_key("pid")
-
-        }).collect::<Vec<_>>();
-
-    let valid = have_fields.iter()
-        .filter(|passport| {
-            validate_usize(passport.get("byr").unwrap(), 1920, 2002) &&
-            validate_usize(passport.get("iyr").unwrap(), 2010, 2020) &&
-            validate_usize(passport.get("eyr").unwrap(), 2020, 2030) &&
-            validate_height(passport.get("hgt").unwrap()) &&
-            validate_hair_color(passport.get("hcl").unwrap()) &&
-            validate_eye_color(passport.get("ecl").unwrap()) &&
-            validate_pid(passport.get("pid").unwrap())
-        })
+    println!("{} Part 1: {}", label, valid);
+}
+
+fn part2(passports: &[Passport], document: &DocumentType, label: &str) {
+    let valid = passports.iter()
+        .filter(|passport| document.is_valid(&passport.fields))
         .count();
 
+    println!("{} Part 2: {}", label, valid);
+}
 
-    println!("Valid Part 1: {}", valid);
+// Only the primary document unless `all` is set
+fn report(passports: &[Passport], schema: &Schema, format: Option<&str>, all: bool) {
+    let documents = if all { &schema.documents[..] } else { std::slice::from_ref(schema.primary()) };
+    for document in documents {
+        let report = report::build(passports, document);
+        match format {
+            Some("json") => print!("{}", report::to_json(&report)),
+            _ => print!("{}", report::to_table(&report)),
+        }
+    }
+}
+
+fn read_file(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn write_file(path: &str, contents: &str) -> Result<(), String> {
+    std::fs::write(path, contents).map_err(|e| format!("{}: {}", path, e))
+}
+
+// The format is picked by the file extension, `.csv` or `.jsonl`
+fn export(passports: &[Passport], schema: &Schema, path: &str) -> Result<(), String> {
+    let records = passports.iter().map(|p| &p.fields).collect::<Vec<_>>();
+    let contents = if path.ends_with(".csv") {
+        convert::to_csv(&records, &convert::columns(&records, schema))
+    } else {
+        convert::to_json_lines(&records)
+    };
+    write_file(path, &contents)
+}
+
+fn import(schema: &Schema, from: &str, to: &str) -> Result<(), String> {
+    let contents = read_file(from)?;
+    let records = if from.ends_with(".csv") {
+        convert::from_csv(&contents)?
+    } else {
+        convert::from_json_lines(&contents)?
+    };
+
+    let records = records.iter().collect::<Vec<_>>();
+    write_file(to, &convert::to_batch(&records, &convert::columns(&records, schema))?)
 }
 
 fn main() {
-    println!("Reading input");
+    // `report [table|json] [--all]` prints a per-passport report against the
+    // primary document, or every document with --all, instead of the counts
+    // `export <file.csv|file.jsonl>` writes the parsed passports to a file
+    // `import <file.csv|file.jsonl> <batch.txt>` converts a file back to the batch format
+    let args = std::env::args().collect::<Vec<_>>();
+    let mode = args.get(1).map(|a| a.as_str());
+    if mode.is_none() {
+        println!("Reading input");
+    }
+
+    let schema = match Schema::load("src/schema.toml") {
+        Ok(schema) => schema,
+        Err(e) => {
+            println!("Failed to load schema: {}", e);
+            return;
+        }
+    };
+
+    if mode == Some("import") {
+        match (args.get(2), args.get(3)) {
+            (Some(from), Some(to)) => {
+                if let Err(e) = import(&schema, from, to) {
+                    println!("Import failed: {}", e);
+                }
+            },
+            _ => println!("Usage: import <file.csv|file.jsonl> <batch.txt>"),
+        }
+        return;
+    }
+
     let input = read_input();
     let entries = split_entries(input);
-    let passports = parse_entries(entries);
-    part1(&passports);
-    part2(&passports);
+    let (passports, warnings) = parse_entries(entries, &schema.known_keys());
+    for w in warnings {
+        eprintln!("Warning: {}", w);
+    }
+
+    match mode {
+        Some("report") => {
+            let format = args.get(2).map(|a| a.as_str()).filter(|a| *a != "--all");
+            report(&passports, &schema, format, args.iter().any(|a| a == "--all"));
+        },
+        Some("export") => match args.get(2) {
+            Some(path) => {
+                if let Err(e) = export(&passports, &schema, path) {
+                    println!("Export failed: {}", e);
+                }
+            },
+            None => println!("Usage: export <file.csv|file.jsonl>"),
+        },
+        Some(m) => println!("Unknown mode {:?}, expected report, export or import", m),
+        None => {
+            for (i, document) in schema.documents.iter().enumerate() {
+                let label = if i == 0 { "Valid" } else { &document.name };
+                part1(&passports, document, label);
+                part2(&passports, document, label);
+            }
+        },
+    }
 }
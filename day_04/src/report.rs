@@ -0,0 +1,62 @@
+use crate::Passport;
+use crate::schema::DocumentType;
+use crate::schema::Failure;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct FieldFailure<'a> {
+    pub field: &'a str,
+    #[serde(flatten)]
+    pub failure: Failure,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PassportReport<'a> {
+    pub document: &'a str,
+    pub first_line: usize,
+    pub last_line: usize,
+    pub valid: bool,
+    pub failures: Vec<FieldFailure<'a>>,
+}
+
+pub fn build<'a>(passports: &[Passport], document: &'a DocumentType) -> Vec<PassportReport<'a>> {
+    passports.iter()
+        .map(|p| {
+            let failures = document.check(&p.fields).into_iter()
+                .map(|(field, failure)| FieldFailure { field, message: failure.to_string(), failure })
+                .collect::<Vec<_>>();
+
+            PassportReport {
+                document: &document.name,
+                first_line: p.first_line,
+                last_line: p.last_line,
+                valid: failures.is_empty(),
+                failures,
+            }
+        })
+        .collect()
+}
+
+pub fn to_table(report: &[PassportReport]) -> String {
+    let mut out = format!("{:<12} {:<24} {:<6} Failures\n", "Lines", "Document", "Valid");
+    for r in report {
+        let failures = r.failures.iter()
+            .map(|f| format!("{}: {}", f.field, f.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        out.push_str(&format!("{:<12} {:<24} {:<6} {}\n",
+                              format!("{}-{}", r.first_line, r.last_line),
+                              r.document,
+                              if r.valid { "yes" } else { "no" },
+                              failures));
+    }
+    out
+}
+
+// One JSON object per line
+pub fn to_json(report: &[PassportReport]) -> String {
+    report.iter()
+        .map(|r| serde_json::to_string(r).unwrap() + "\n")
+        .collect()
+}
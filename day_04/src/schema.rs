@@ -0,0 +1,262 @@
+use regex::Regex;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum ValidatorSpec {
+    Range { min: u64, max: u64 },
+    UnitRange { units: BTreeMap<String, (u64, u64)> },
+    Regex { pattern: String },
+    Enum { values: Vec<String> },
+    Digits { count: usize },
+}
+
+// `required` defaults to true, or to the inherited value when overriding a field
+#[derive(Debug, Clone, Deserialize)]
+struct FieldSpec {
+    key: String,
+    required: Option<bool>,
+    validator: Option<ValidatorSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DocumentSpec {
+    name: String,
+    extends: Option<String>,
+    #[serde(default)]
+    field: Vec<FieldSpec>,
+}
+
+// A document's fields with the ones it inherits, in order: inherited fields
+// first, each replaced by a field with the same key, then the new fields.
+// An override only changes what it sets.
+fn resolve_fields(document: &DocumentSpec, resolved: &[(String, Vec<FieldSpec>)]) -> Result<Vec<FieldSpec>, String> {
+    let mut fields = match &document.extends {
+        Some(base) => resolved.iter()
+            .find(|(name, _)| name == base)
+            .map(|(_, fields)| fields.clone())
+            .ok_or_else(|| format!("{}: extends {:?}, which isn't defined before it", document.name, base))?,
+        None => vec![],
+    };
+
+    for f in &document.field {
+        match fields.iter_mut().find(|inherited| inherited.key == f.key) {
+            Some(inherited) => {
+                if f.required.is_some() {
+                    inherited.required = f.required;
+                }
+                if f.validator.is_some() {
+                    inherited.validator = f.validator.clone();
+                }
+            },
+            None => fields.push(f.clone()),
+        }
+    }
+    Ok(fields)
+}
+
+#[derive(Debug, Deserialize)]
+struct SchemaSpec {
+    document: Vec<DocumentSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Failure {
+    Missing,
+    BadFormat { expected: String },
+    OutOfRange { value: u64, min: u64, max: u64 },
+    WrongUnit { unit: String, expected: Vec<String> },
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Missing => write!(f, "missing"),
+            Failure::BadFormat { expected } => write!(f, "bad format, expected {}", expected),
+            Failure::OutOfRange { value, min, max } =>
+                write!(f, "{} out of range {}-{}", value, min, max),
+            Failure::WrongUnit { unit, expected } =>
+                write!(f, "wrong unit {:?}, expected one of {}", unit, expected.join(", ")),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Validator {
+    Range { min: u64, max: u64 },
+    UnitRange { units: BTreeMap<String, (u64, u64)> },
+    Regex { pattern: String, re: Regex },
+    Enum { values: Vec<String> },
+    Digits { count: usize },
+}
+
+fn check_range(value: u64, min: u64, max: u64) -> Result<(), Failure> {
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(Failure::OutOfRange { value, min, max })
+    }
+}
+
+impl Validator {
+    fn compile(spec: ValidatorSpec) -> Result<Validator, String> {
+        Ok(match spec {
+            ValidatorSpec::Range { min, max } => Validator::Range { min, max },
+            ValidatorSpec::UnitRange { units } => Validator::UnitRange { units },
+            ValidatorSpec::Regex { pattern } => {
+                let re = Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| e.to_string())?;
+                Validator::Regex { pattern, re }
+            },
+            ValidatorSpec::Enum { values } => Validator::Enum { values },
+            ValidatorSpec::Digits { count } => Validator::Digits { count },
+        })
+    }
+
+    fn check(&self, value: &str) -> Result<(), Failure> {
+        match self {
+            Validator::Range { min, max } => {
+                let n = value.parse::<u64>()
+                    .map_err(|_| Failure::BadFormat { expected: "a number".to_string() })?;
+                check_range(n, *min, *max)
+            },
+            Validator::UnitRange { units } => {
+                let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+                let (num, unit) = value.split_at(split);
+                let n = num.parse::<u64>()
+                    .map_err(|_| Failure::BadFormat { expected: "a number followed by a unit".to_string() })?;
+                match units.get(unit) {
+                    Some((min, max)) => check_range(n, *min, *max),
+                    None => Err(Failure::WrongUnit {
+                        unit: unit.to_string(),
+                        expected: units.keys().cloned().collect(),
+                    }),
+                }
+            },
+            Validator::Regex { pattern, re } => {
+                if re.is_match(value) {
+                    Ok(())
+                } else {
+                    Err(Failure::BadFormat { expected: format!("/{}/", pattern) })
+                }
+            },
+            Validator::Enum { values } => {
+                if values.iter().any(|v| v == value) {
+                    Ok(())
+                } else {
+                    Err(Failure::BadFormat { expected: format!("one of {}", values.join(", ")) })
+                }
+            },
+            Validator::Digits { count } => {
+                if value.len() == *count && value.chars().all(|c| c.is_ascii_digit()) {
+                    Ok(())
+                } else {
+                    Err(Failure::BadFormat { expected: format!("{} digits", count) })
+                }
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub key: String,
+    pub required: bool,
+    validator: Option<Validator>,
+}
+
+#[derive(Debug)]
+pub struct DocumentType {
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+impl DocumentType {
+    pub fn has_required_fields(&self, passport: &HashMap<String, String>) -> bool {
+        self.fields.iter()
+            .filter(|f| f.required)
+            .all(|f| passport.contains_key(&f.key))
+    }
+
+    // Every failing field, in schema order. Optional fields are only checked when present.
+    pub fn check<'a>(&'a self, passport: &HashMap<String, String>) -> Vec<(&'a str, Failure)> {
+        self.fields.iter()
+            .filter_map(|f| {
+                let res = match passport.get(&f.key) {
+                    None if f.required => Err(Failure::Missing),
+                    None => Ok(()),
+                    Some(value) => f.validator.as_ref().map_or(Ok(()), |v| v.check(value)),
+                };
+                res.err().map(|e| (f.key.as_str(), e))
+            })
+            .collect()
+    }
+
+    pub fn is_valid(&self, passport: &HashMap<String, String>) -> bool {
+        self.check(passport).is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct Schema {
+    pub documents: Vec<DocumentType>,
+}
+
+impl Schema {
+    pub fn parse(contents: &str) -> Result<Schema, String> {
+        let spec = toml::from_str::<SchemaSpec>(contents).map_err(|e| e.to_string())?;
+        let mut resolved: Vec<(String, Vec<FieldSpec>)> = vec![];
+        for document in &spec.document {
+            if resolved.iter().any(|(name, _)| *name == document.name) {
+                return Err(format!("{}: defined more than once", document.name));
+            }
+            let fields = resolve_fields(document, &resolved)?;
+            resolved.push((document.name.clone(), fields));
+        }
+
+        let documents = resolved.into_iter()
+            .map(|(name, fields)| {
+                let fields = fields.into_iter()
+                    .map(|FieldSpec { key, required, validator }| {
+                        let validator = validator.map(Validator::compile).transpose()
+                            .map_err(|e| format!("{}.{}: {}", name, key, e))?;
+                        Ok(Field { key, required: required.unwrap_or(true), validator })
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(DocumentType { name, fields })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if documents.is_empty() {
+            return Err("no documents".to_string());
+        }
+        Ok(Schema { documents })
+    }
+
+    // The document type the puzzle answers are for
+    pub fn primary(&self) -> &DocumentType {
+        &self.documents[0]
+    }
+
+    // Every field key used by any of the document types
+    pub fn known_keys(&self) -> HashSet<&str> {
+        self.documents.iter()
+            .flat_map(|d| d.fields.iter().map(|f| f.key.as_str()))
+            .collect()
+    }
+
+    pub fn load(path: &str) -> Result<Schema, String> {
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        Schema::parse(&contents)
+    }
+}
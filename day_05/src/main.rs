@@ -1,64 +1,337 @@
-use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::fs::File;
+use std::ops::RangeInclusive;
 
-fn read_input() -> Vec<(usize, usize)> {
-    let input_file = File::open("src/input.txt").unwrap();
-    let reader = BufReader::new(input_file);
-    reader.lines()
-        .map(|x|x.unwrap())
-        .map(|l|map_entry(l.split_at(7)))
-        .collect::<Vec<_>>()
+// One axis of a binary space partition: `bits` letters, each either `zero`
+// (lower half) or `one` (upper half), most significant first
+#[derive(Debug, Clone, Copy)]
+struct Axis {
+    zero: char,
+    one: char,
+    bits: u32,
+}
+
+// Positions are 0-based character offsets into the whole pass
+#[derive(Debug, PartialEq, Eq)]
+enum PassError {
+    WrongLength { expected: usize, found: usize },
+    BadLetter { position: usize, letter: char, expected: (char, char) },
 }
 
-fn map_entry((r,c): (&str, &str)) -> (usize, usize) {
-    (map_row(r), map_col(c))
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PassError::WrongLength { expected, found } =>
+                write!(f, "expected {} letters, found {}", expected, found),
+            PassError::BadLetter { position, letter, expected } =>
+                write!(f, "position {}: {:?} is not {:?} or {:?}", position + 1, letter, expected.0, expected.1),
+        }
+    }
 }
 
-fn map_row(r: &str) -> usize {
-    r.chars()
-        .map(|c| if c == 'F' {0} else {1} )
-        .fold(0, |acc, b| { acc * 2 + b })
+impl Axis {
+    // `offset` is where `s` starts within the pass, for error positions
+    fn decode(&self, s: &str, offset: usize) -> Result<usize, PassError> {
+        s.chars()
+            .enumerate()
+            .map(|(i, c)| match c {
+                c if c == self.zero => Ok(0),
+                c if c == self.one => Ok(1),
+                _ => Err(PassError::BadLetter { position: offset + i, letter: c, expected: (self.zero, self.one) }),
+            })
+            .try_fold(0, |acc, b| Ok(acc * 2 + b?))
+    }
+
+    // None if `n` needs more than `bits` letters
+    fn encode(&self, n: usize) -> Option<String> {
+        if n >= self.size() {
+            return None;
+        }
+        Some((0..self.bits).rev()
+             .map(|bit| if n >> bit & 1 == 0 { self.zero } else { self.one })
+             .collect())
+    }
+
+    fn size(&self) -> usize {
+        1 << self.bits
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CodecError {
+    SameLetters(char),
+    TooManyBits { bits: u32, max: u32 },
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodecError::SameLetters(c) => write!(f, "an axis uses {:?} for both halves", c),
+            CodecError::TooManyBits { bits, max } =>
+                write!(f, "{} bits in total, seat IDs only fit {}", bits, max),
+        }
+    }
 }
 
-fn map_col(c: &str) -> usize {
-    c.chars()
-        .map(|c| if c == 'L' {0} else {1} )
-        .fold(0, |acc, b| { acc * 2 + b })
+#[derive(Debug, Clone, Copy)]
+struct Codec {
+    row: Axis,
+    col: Axis,
+}
+
+impl Codec {
+    // Each axis needs two different letters, and a seat ID has to fit in a usize
+    fn new(row: Axis, col: Axis) -> Result<Codec, CodecError> {
+        for axis in &[row, col] {
+            if axis.zero == axis.one {
+                return Err(CodecError::SameLetters(axis.zero));
+            }
+        }
+
+        let max = usize::BITS - 1;
+        let bits = row.bits.saturating_add(col.bits);
+        if bits > max {
+            return Err(CodecError::TooManyBits { bits, max });
+        }
+        Ok(Codec { row, col })
+    }
+
+    // 128 rows by 8 columns, as on the puzzle's aircraft
+    fn standard() -> Codec {
+        Codec::new(Axis { zero: 'F', one: 'B', bits: 7 },
+                   Axis { zero: 'L', one: 'R', bits: 3 })
+            .unwrap()
+    }
+
+    fn decode(&self, pass: &str) -> Result<(usize, usize), PassError> {
+        let row_len = self.row.bits as usize;
+        let expected = row_len + self.col.bits as usize;
+        let found = pass.chars().count();
+        if found != expected {
+            return Err(PassError::WrongLength { expected, found });
+        }
+
+        let split = pass.char_indices().nth(row_len).map_or(pass.len(), |(i, _)| i);
+        let (r, c) = pass.split_at(split);
+        Ok((self.row.decode(r, 0)?, self.col.decode(c, row_len)?))
+    }
+
+    // None if the seat isn't on the plane
+    fn encode(&self, (r, c): (usize, usize)) -> Option<String> {
+        Some(self.row.encode(r)? + &self.col.encode(c)?)
+    }
+
+    fn seat_id(&self, (r, c): &(usize, usize)) -> usize {
+        r * self.col.size() + c
+    }
+
+    // None if the seat ID is past the last row
+    fn seat(&self, seat_id: usize) -> Option<(usize, usize)> {
+        let seat = (seat_id / self.col.size(), seat_id % self.col.size());
+        if seat.0 < self.row.size() { Some(seat) } else { None }
+    }
+
+    fn encode_seat_id(&self, seat_id: usize) -> Option<String> {
+        self.encode(self.seat(seat_id)?)
+    }
 }
 
-fn calc_seat_id((r,c): &(usize, usize)) -> usize {
-    r * 8 + c
+// (row, col)
+type Seat = (usize, usize);
+
+#[derive(Debug, Default)]
+struct Validation {
+    check_duplicates: bool,
+    seat_ids: Option<RangeInclusive<usize>>,
 }
 
+#[derive(Debug)]
+enum LineError {
+    Invalid(PassError),
+    OutOfRange { seat_id: usize },
+    Duplicate { seat_id: usize, first_line: usize },
+}
 
-fn part_1(input: &Vec<(usize, usize)>) {
-    let res = input.iter()
-        .map(|e| calc_seat_id(e))
-        .max()
-        .unwrap();
-    println!("Part 1: {}", res);
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LineError::Invalid(e) => write!(f, "{}", e),
+            LineError::OutOfRange { seat_id } => write!(f, "seat ID {} is out of range", seat_id),
+            LineError::Duplicate { seat_id, first_line } =>
+                write!(f, "seat ID {} was already seen on line {}", seat_id, first_line),
+        }
+    }
 }
 
-fn part_2(input: &Vec<(usize, usize)>) {
-    let mut seat_ids = input.iter()
-        .map(|e| calc_seat_id(e))
+// Invalid passes are left out of the result; out of range and duplicate seats
+// are reported but kept. Line numbers are 1-based.
+fn read_input(codec: &Codec, validation: &Validation) -> (Vec<Seat>, Vec<(usize, LineError)>) {
+    let input_file = File::open("src/input.txt").unwrap();
+    let reader = BufReader::new(input_file);
+    let mut errors = vec![];
+    let mut seen = HashMap::new();
+    let seats = reader.lines()
+        .map(|x|x.unwrap())
+        .enumerate()
+        .filter_map(|(i, l)| {
+            let line = i + 1;
+            let seat = match codec.decode(&l) {
+                Ok(seat) => seat,
+                Err(e) => {
+                    errors.push((line, LineError::Invalid(e)));
+                    return None;
+                }
+            };
+
+            let seat_id = codec.seat_id(&seat);
+            if let Some(range) = &validation.seat_ids {
+                if !range.contains(&seat_id) {
+                    errors.push((line, LineError::OutOfRange { seat_id }));
+                }
+            }
+            if validation.check_duplicates {
+                if let Some(first_line) = seen.get(&seat_id) {
+                    errors.push((line, LineError::Duplicate { seat_id, first_line: *first_line }));
+                } else {
+                    seen.insert(seat_id, line);
+                }
+            }
+            Some(seat)
+        })
         .collect::<Vec<_>>();
 
-    seat_ids.sort();
+    (seats, errors)
+}
 
-    println!("IDs: {:?}", seat_ids);
-    for idx in 1..seat_ids.len() {
-        if seat_ids[idx-1] + 2 == seat_ids[idx] {
-            println!("Res: {}", seat_ids[idx] - 1);
+fn part_1(input: &[(usize, usize)], codec: &Codec) {
+    match input.iter().map(|e| codec.seat_id(e)).max() {
+        Some(res) => println!("Part 1: {} ({})", res, codec.encode_seat_id(res).unwrap()),
+        None => println!("Part 1: no valid boarding passes"),
+    }
+}
+
+// Number of boarding passes for each seat, indexed by [row][col]
+#[derive(Debug)]
+struct SeatMap {
+    codec: Codec,
+    passes: Vec<Vec<usize>>,
+}
+
+impl SeatMap {
+    // Seats outside of the plane are ignored
+    fn new(codec: &Codec, seats: &[(usize, usize)]) -> SeatMap {
+        let mut passes = vec![vec![0; codec.col.size()]; codec.row.size()];
+        for (r, c) in seats {
+            if let Some(count) = passes.get_mut(*r).and_then(|row| row.get_mut(*c)) {
+                *count += 1;
+            }
         }
+        SeatMap { codec: *codec, passes }
+    }
+
+    fn is_occupied(&self, seat_id: usize) -> bool {
+        self.codec.seat(seat_id).is_some_and(|(r, c)| self.passes[r][c] > 0)
+    }
+
+    // `#` occupied, `.` free, `!` more than one pass for the same seat
+    fn render(&self) -> String {
+        let header = (0..self.codec.col.size())
+            .map(|c| std::char::from_digit((c % 10) as u32, 10).unwrap())
+            .collect::<String>();
+        let mut out = format!("{:>4} {:>w$} {}\n", "", "", header, w = self.codec.row.bits as usize);
+        for (r, row) in self.passes.iter().enumerate() {
+            let seats = row.iter()
+                .map(|n| match n { 0 => '.', 1 => '#', _ => '!' })
+                .collect::<String>();
+            out.push_str(&format!("{:>4} {} {}\n", r, self.codec.row.encode(r).unwrap(), seats));
+        }
+        out
+    }
+
+    // Free seat IDs whose IDs +1 and -1 both have a pass
+    fn free_between_occupied(&self) -> Vec<usize> {
+        let seats = self.codec.row.size() * self.codec.col.size();
+        (1..seats.saturating_sub(1))
+            .filter(|id| !self.is_occupied(*id))
+            .filter(|id| self.is_occupied(id - 1) && self.is_occupied(id + 1))
+            .collect()
+    }
+
+    // Every maximal run of at least `n` adjacent free seats in a row, as (row, first col, length)
+    fn free_runs(&self, n: usize) -> Vec<(usize, usize, usize)> {
+        let mut runs = vec![];
+        for (r, row) in self.passes.iter().enumerate() {
+            let mut start = None;
+            for c in 0..=row.len() {
+                let free = c < row.len() && row[c] == 0;
+                match (free, start) {
+                    (true, None) => start = Some(c),
+                    (false, Some(s)) => {
+                        if c - s >= n { runs.push((r, s, c - s)); }
+                        start = None;
+                    },
+                    _ => {},
+                }
+            }
+        }
+        runs
+    }
+
+    // Seats with more than one pass, as (seat, number of passes)
+    fn duplicates(&self) -> Vec<((usize, usize), usize)> {
+        self.passes.iter()
+            .enumerate()
+            .flat_map(|(r, row)| row.iter()
+                      .enumerate()
+                      .filter(|(_, n)| **n > 1)
+                      .map(move |(c, n)| ((r, c), *n)))
+            .collect()
     }
 }
 
+fn part_2(seat_map: &SeatMap, codec: &Codec) {
+    for seat_id in seat_map.free_between_occupied() {
+        println!("Res: {} ({})", seat_id, codec.encode_seat_id(seat_id).unwrap());
+    }
+}
+
+fn seat_map_report(seat_map: &SeatMap) {
+    print!("{}", seat_map.render());
+
+    println!("Free runs of 3 or more seats: {}", seat_map.free_runs(3).len());
+    println!("Duplicate passes: {:?}", seat_map.duplicates());
+}
+
 fn main() {
     println!("Reading input");
-    let input = read_input();
-    part_1(&input);
-    part_2(&input);
+    let codec = Codec::standard();
+    // --check-duplicates and --seat-ids=MIN-MAX turn on the optional checks,
+    // --seat-map prints the seat chart with free runs and duplicate passes
+    let validation = std::env::args().skip(1)
+        .fold(Validation::default(), |mut acc, arg| {
+            if arg == "--check-duplicates" {
+                acc.check_duplicates = true;
+            } else if let Some(range) = arg.strip_prefix("--seat-ids=") {
+                let bounds = range.split('-').map(|n| n.parse::<usize>()).collect::<Vec<_>>();
+                match bounds.as_slice() {
+                    [Ok(low), Ok(high)] => acc.seat_ids = Some(*low..=*high),
+                    _ => println!("Ignoring bad seat ID range {:?}", range),
+                }
+            }
+            acc
+        });
+
+    let (input, errors) = read_input(&codec, &validation);
+    for (line, e) in &errors {
+        println!("Line {}: {}", line, e);
+    }
+    let seat_map = SeatMap::new(&codec, &input);
+    part_1(&input, &codec);
+    part_2(&seat_map, &codec);
+    if std::env::args().any(|a| a == "--seat-map") {
+        seat_map_report(&seat_map);
+    }
 }
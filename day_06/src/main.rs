@@ -1,8 +1,9 @@
-use regex::Regex;
-use std::collections::HashMap;
+mod report;
+
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::fs::File;
+use std::path::Path;
 
 fn read_input() -> Vec<Vec<String>> {
     let input_file = File::open("src/input.txt").unwrap();
@@ -22,34 +23,180 @@ fn read_input() -> Vec<Vec<String>> {
         })
 }
 
-fn process_group(group: &Vec<String>) -> HashMap<char, usize> {
-    group.join("")
-        .chars()
-        .fold(HashMap::new(), |mut acc, c| {
-            (*acc.entry(c).or_insert(0)) += 1;
-            acc
-        })
+const QUESTIONS: usize = 26;
+
+// One bit per question, bit 0 for 'a'. Anything outside 'a'..='z' is ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Answers(u32);
+
+impl Answers {
+    const ALL: Answers = Answers((1 << QUESTIONS) - 1);
+
+    fn new(s: &str) -> Answers {
+        Answers(s.chars()
+            .filter(|c| c.is_ascii_lowercase())
+            .fold(0, |acc, c| acc | 1 << (c as u32 - 'a' as u32)))
+    }
+
+    fn count(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    fn contains(&self, question: usize) -> bool {
+        self.0 >> question & 1 == 1
+    }
+
+    fn union(&self, other: &Answers) -> Answers {
+        Answers(self.0 | other.0)
+    }
+
+    fn intersection(&self, other: &Answers) -> Answers {
+        Answers(self.0 & other.0)
+    }
+
+    fn complement(&self) -> Answers {
+        Answers(!self.0 & Answers::ALL.0)
+    }
+
+    fn questions(&self) -> String {
+        (0..QUESTIONS)
+            .filter(|q| self.contains(*q))
+            .map(|q| (b'a' + q as u8) as char)
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct Group {
+    people: Vec<Answers>,
 }
 
-fn part1(input: &Vec<Vec<String>>) {
-    let result:usize = input.iter()
-        .map(|entry| process_group(entry).len())
+impl Group {
+    fn new(lines: &[String]) -> Group {
+        Group { people: lines.iter().map(|l| Answers::new(l)).collect() }
+    }
+
+    // Number of people who answered each question
+    fn counts(&self) -> [usize; QUESTIONS] {
+        let mut counts = [0; QUESTIONS];
+        for person in &self.people {
+            for (q, count) in counts.iter_mut().enumerate() {
+                if person.contains(q) { *count += 1; }
+            }
+        }
+        counts
+    }
+
+    fn matching(&self, f: impl Fn(usize) -> bool) -> Answers {
+        Answers(self.counts().iter()
+            .enumerate()
+            .filter(|(_, n)| f(**n))
+            .fold(0, |acc, (q, _)| acc | 1 << q))
+    }
+
+    fn anyone(&self) -> Answers {
+        self.people.iter().fold(Answers::default(), |acc, p| acc.union(p))
+    }
+
+    fn everyone(&self) -> Answers {
+        if self.people.is_empty() {
+            return Answers::default();
+        }
+        self.people.iter().fold(Answers::ALL, |acc, p| acc.intersection(p))
+    }
+
+    fn exactly(&self, k: usize) -> Answers {
+        if k == 0 {
+            return self.nobody();
+        }
+        self.matching(|n| n == k)
+    }
+
+    // Questions answered by more than half of the group
+    fn majority(&self) -> Answers {
+        let size = self.people.len();
+        self.matching(|n| n * 2 > size)
+    }
+
+    fn nobody(&self) -> Answers {
+        self.anyone().complement()
+    }
+}
+
+// Per question totals across all groups
+#[derive(Debug, Default)]
+struct QuestionStats {
+    people: usize,
+    groups_anyone: usize,
+    groups_everyone: usize,
+    groups_majority: usize,
+}
+
+fn question_stats(groups: &[Group]) -> Vec<QuestionStats> {
+    let mut stats = (0..QUESTIONS).map(|_| QuestionStats::default()).collect::<Vec<_>>();
+    for group in groups {
+        let counts = group.counts();
+        let (anyone, everyone, majority) = (group.anyone(), group.everyone(), group.majority());
+        for (q, s) in stats.iter_mut().enumerate() {
+            s.people += counts[q];
+            if anyone.contains(q) { s.groups_anyone += 1; }
+            if everyone.contains(q) { s.groups_everyone += 1; }
+            if majority.contains(q) { s.groups_majority += 1; }
+        }
+    }
+    stats
+}
+
+fn part1(groups: &[Group]) {
+    let result:usize = groups.iter()
+        .map(|group| group.anyone().count())
         .sum();
     println!("Valid Part 1: {:?}", result);
 }
 
-fn part2(input: &Vec<Vec<String>>) {
-    let result:usize = input.iter()
-        .map(|entry| process_group(entry).iter()
-                .filter(|(_, v)| **v == entry.len())
-                .count())
+fn part2(groups: &[Group]) {
+    let result:usize = groups.iter()
+        .map(|group| group.everyone().count())
         .sum();
     println!("Valid Part 2: {}", result);
 }
 
+fn queries(groups: &[Group]) {
+    let exactly_one:usize = groups.iter().map(|g| g.exactly(1).count()).sum();
+    let majority:usize = groups.iter().map(|g| g.majority().count()).sum();
+    let nobody = groups.iter()
+        .fold(Answers::default(), |acc, g| acc.union(&g.anyone()))
+        .complement();
+    println!("Answered by exactly one person: {}", exactly_one);
+    println!("Answered by a majority: {}", majority);
+    println!("Answered by nobody in any group: {:?}", nobody.questions());
+}
+
+// `csv_dir` is where to write the CSV report, if anywhere
+fn statistics(groups: &[Group], csv_dir: Option<&str>) {
+    report::print_summary(groups);
+
+    if let Some(dir) = csv_dir {
+        match report::write_csv(groups, Path::new(dir)) {
+            Ok(()) => println!("Wrote CSV report to {}", dir),
+            Err(e) => println!("Failed to write CSV report to {}: {}", dir, e),
+        }
+    }
+}
+
 fn main() {
     println!("Reading input");
+    // --csv writes the CSV report to target/report, --csv=DIR to DIR
+    let csv_dir = std::env::args().skip(1)
+        .rev()
+        .find_map(|a| match a.as_str() {
+            "--csv" => Some("target/report".to_string()),
+            _ => a.strip_prefix("--csv=").map(|d| d.to_string()),
+        });
     let input = read_input();
-    part1(&input);
-    part2(&input);
+    let groups = input.iter().map(|g| Group::new(g)).collect::<Vec<_>>();
+    part1(&groups);
+    part2(&groups);
+    queries(&groups);
+    statistics(&groups, csv_dir.as_deref());
 }
@@ -0,0 +1,110 @@
+use crate::question_stats;
+use crate::Group;
+use crate::QUESTIONS;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+fn question_name(q: usize) -> char {
+    (b'a' + q as u8) as char
+}
+
+// Share of the groups where someone answered the question in which everyone did
+fn agreement(groups: &[Group]) -> Vec<f64> {
+    question_stats(groups).iter()
+        .map(|s| if s.groups_anyone == 0 { 0.0 } else { s.groups_everyone as f64 / s.groups_anyone as f64 })
+        .collect()
+}
+
+fn questions_csv(groups: &[Group]) -> String {
+    let people = groups.iter().map(|g| g.people.len()).sum::<usize>();
+    let agreement = agreement(groups);
+    let mut out = "question,people,people_share,groups_anyone,groups_everyone,groups_majority,agreement\n".to_string();
+    for (q, s) in question_stats(groups).iter().enumerate() {
+        out.push_str(&format!("{},{},{:.4},{},{},{},{:.4}\n",
+                              question_name(q),
+                              s.people,
+                              if people == 0 { 0.0 } else { s.people as f64 / people as f64 },
+                              s.groups_anyone,
+                              s.groups_everyone,
+                              s.groups_majority,
+                              agreement[q]));
+    }
+    out
+}
+
+fn group_sizes(groups: &[Group]) -> BTreeMap<usize, usize> {
+    groups.iter()
+        .fold(BTreeMap::new(), |mut acc, g| {
+            *acc.entry(g.people.len()).or_insert(0) += 1;
+            acc
+        })
+}
+
+fn group_sizes_csv(groups: &[Group]) -> String {
+    let mut out = "size,groups\n".to_string();
+    for (size, count) in group_sizes(groups) {
+        out.push_str(&format!("{},{}\n", size, count));
+    }
+    out
+}
+
+// Phi coefficient between every pair of questions, treating each person as a sample.
+// Pairs where either question is answered by everyone or nobody have no correlation.
+fn correlations(groups: &[Group]) -> Vec<(usize, usize, Option<f64>)> {
+    let people = groups.iter().flat_map(|g| g.people.iter()).collect::<Vec<_>>();
+    let n = people.len() as f64;
+    let mut result = vec![];
+    for a in 0..QUESTIONS {
+        for b in a + 1..QUESTIONS {
+            let both = people.iter().filter(|p| p.contains(a) && p.contains(b)).count() as f64;
+            let with_a = people.iter().filter(|p| p.contains(a)).count() as f64;
+            let with_b = people.iter().filter(|p| p.contains(b)).count() as f64;
+            let denominator = (with_a * (n - with_a) * with_b * (n - with_b)).sqrt();
+            let phi = if denominator == 0.0 {
+                None
+            } else {
+                Some((both * n - with_a * with_b) / denominator)
+            };
+            result.push((a, b, phi));
+        }
+    }
+    result
+}
+
+fn correlations_csv(groups: &[Group]) -> String {
+    let mut out = "question_a,question_b,phi\n".to_string();
+    for (a, b, phi) in correlations(groups) {
+        let phi = phi.map_or(String::new(), |p| format!("{:.4}", p));
+        out.push_str(&format!("{},{},{}\n", question_name(a), question_name(b), phi));
+    }
+    out
+}
+
+// Writes questions.csv, group_sizes.csv and correlations.csv into `dir`
+pub fn write_csv(groups: &[Group], dir: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("questions.csv"), questions_csv(groups))?;
+    fs::write(dir.join("group_sizes.csv"), group_sizes_csv(groups))?;
+    fs::write(dir.join("correlations.csv"), correlations_csv(groups))?;
+    Ok(())
+}
+
+pub fn print_summary(groups: &[Group]) {
+    let agreement = agreement(groups);
+    let mut by_agreement = (0..QUESTIONS).collect::<Vec<_>>();
+    by_agreement.sort_by(|a, b| agreement[*b].partial_cmp(&agreement[*a]).unwrap());
+    let most = by_agreement[0];
+    let least = by_agreement[QUESTIONS - 1];
+    println!("Most agreed question: {} ({:.1}%)", question_name(most), agreement[most] * 100.0);
+    println!("Least agreed question: {} ({:.1}%)", question_name(least), agreement[least] * 100.0);
+
+    println!("Group sizes: {:?}", group_sizes(groups));
+
+    let strongest = correlations(groups).into_iter()
+        .filter_map(|(a, b, phi)| phi.map(|p| (a, b, p)))
+        .max_by(|x, y| x.2.abs().partial_cmp(&y.2.abs()).unwrap());
+    if let Some((a, b, phi)) = strongest {
+        println!("Strongest correlation: {} and {} ({:.4})", question_name(a), question_name(b), phi);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::graph::BagGraph;
+use crate::graph::GraphError;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Nodes and edges are sorted so exports of the same rules diff cleanly. With a
+// `highlight` color, it and its ancestors and descendants are filled in.
+pub fn to_dot(graph: &BagGraph, highlight: Option<&str>) -> Result<String, GraphError> {
+    let (ancestors, descendants) = match highlight {
+        Some(color) => (graph.containers(color)?, graph.descendants(color)?),
+        None => (HashSet::new(), HashSet::new()),
+    };
+
+    let mut colors = graph.rules().keys().collect::<Vec<_>>();
+    colors.sort();
+
+    let mut out = "digraph bags {\n".to_string();
+    for color in &colors {
+        let fill = if highlight == Some(color.as_str()) {
+            Some("gold")
+        } else if ancestors.contains(color.as_str()) {
+            Some("lightblue")
+        } else if descendants.contains(color.as_str()) {
+            Some("lightgreen")
+        } else {
+            None
+        };
+
+        match fill {
+            Some(fill) => out.push_str(&format!("    {} [style=filled, fillcolor={}];\n", quote(color), fill)),
+            None => out.push_str(&format!("    {};\n", quote(color))),
+        }
+    }
+
+    for color in &colors {
+        let mut contents = graph.rules()[*color].iter().collect::<Vec<_>>();
+        contents.sort();
+        for (inner, n) in contents {
+            out.push_str(&format!("    {} -> {} [label=\"{}\"];\n", quote(color), quote(inner), n));
+        }
+    }
+
+    out.push_str("}\n");
+    Ok(out)
+}
+
+// Adjacency lists keyed by color: {"color": [{"color": ..., "count": ...}, ...]}
+pub fn to_json(graph: &BagGraph) -> String {
+    let adjacency = graph.rules().iter()
+        .map(|(color, contents)| {
+            let mut contents = contents.iter().collect::<Vec<_>>();
+            contents.sort();
+            let edges = contents.into_iter()
+                .map(|(inner, n)| json!({ "color": inner, "count": n }))
+                .collect::<Vec<_>>();
+            (color, edges)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    serde_json::to_string_pretty(&adjacency).unwrap() + "\n"
+}
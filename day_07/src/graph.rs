@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fmt;
+
+pub type Rules = HashMap<String, Vec<(String, usize)>>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GraphError {
+    // `referenced_by` is None when the color was asked for directly
+    UndefinedColor { color: String, referenced_by: Option<String> },
+    // Colors along the cycle, starting and ending with the same color
+    Cycle(Vec<String>),
+    Overflow { color: String },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::UndefinedColor { color, referenced_by: None } =>
+                write!(f, "no rule for {:?}", color),
+            GraphError::UndefinedColor { color, referenced_by: Some(by) } =>
+                write!(f, "no rule for {:?}, referenced by {:?}", color, by),
+            GraphError::Cycle(colors) => write!(f, "cycle: {}", colors.join(" -> ")),
+            GraphError::Overflow { color } => write!(f, "bag count inside {:?} overflows", color),
+        }
+    }
+}
+
+fn invert_rules(rules: &Rules) -> HashMap<String, Vec<String>> {
+    rules.iter()
+        .flat_map(|rule| rule.1.iter()
+                  .map(|c| (c.0.clone(), rule.0.clone()))
+                  .collect::<Vec<_>>())
+        .fold(HashMap::new(), |mut acc, (c1, c2)| {
+            (*acc.entry(c1).or_insert(Vec::new())).push(c2);
+            acc
+        })
+}
+
+#[derive(Debug)]
+pub struct Tree {
+    pub color: String,
+    // Number of these bags directly inside the parent bag
+    pub count: usize,
+    pub children: Vec<Tree>,
+}
+
+impl Tree {
+    // One bag per line, indented by nesting level
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut stack = vec![(self, 0)];
+        while let Some((node, level)) = stack.pop() {
+            out.push_str(&format!("{}{} {}\n", "  ".repeat(level), node.count, node.color));
+            stack.extend(node.children.iter().rev().map(|c| (c, level + 1)));
+        }
+        out
+    }
+}
+
+#[derive(Debug)]
+pub struct BagGraph {
+    rules: Rules,
+    inverted: HashMap<String, Vec<String>>,
+}
+
+impl BagGraph {
+    // Every color that appears inside a bag must have its own rule
+    pub fn new(rules: Rules) -> Result<BagGraph, GraphError> {
+        let mut colors = rules.keys().collect::<Vec<_>>();
+        colors.sort();
+        for color in colors {
+            for (inner, _) in &rules[color] {
+                if !rules.contains_key(inner) {
+                    return Err(GraphError::UndefinedColor {
+                        color: inner.clone(),
+                        referenced_by: Some(color.clone()),
+                    });
+                }
+            }
+        }
+
+        let inverted = invert_rules(&rules);
+        Ok(BagGraph { rules, inverted })
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    fn contents(&self, color: &str) -> Result<&Vec<(String, usize)>, GraphError> {
+        self.rules.get(color)
+            .ok_or_else(|| GraphError::UndefinedColor { color: color.to_string(), referenced_by: None })
+    }
+
+    // Colors reachable from `roots` with every color after all the colors it
+    // contains. Iterative, so deep rule chains can't overflow the stack.
+    fn post_order<'a>(&'a self, roots: &[&'a str]) -> Result<Vec<&'a str>, GraphError> {
+        let mut order = vec![];
+        let mut done = HashSet::new();
+
+        for root in roots {
+            if done.contains(root) { continue; }
+            self.contents(root)?;
+
+            let mut stack: Vec<(&str, usize)> = vec![(root, 0)];
+            let mut on_stack = HashSet::new();
+            on_stack.insert(*root);
+
+            while let Some(&(current, next)) = stack.last() {
+                match self.rules[current].get(next) {
+                    Some((child, _)) => {
+                        stack.last_mut().unwrap().1 += 1;
+                        let child = child.as_str();
+                        if done.contains(child) { continue; }
+
+                        if on_stack.contains(child) {
+                            let start = stack.iter().position(|(c, _)| *c == child).unwrap();
+                            let mut cycle = stack[start..].iter()
+                                .map(|(c, _)| c.to_string())
+                                .collect::<Vec<_>>();
+                            cycle.push(child.to_string());
+                            return Err(GraphError::Cycle(cycle));
+                        }
+
+                        on_stack.insert(child);
+                        stack.push((child, 0));
+                    },
+                    None => {
+                        stack.pop();
+                        on_stack.remove(current);
+                        done.insert(current);
+                        order.push(current);
+                    },
+                }
+            }
+        }
+
+        Ok(order)
+    }
+
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut colors = self.rules.keys().map(|c| c.as_str()).collect::<Vec<_>>();
+        colors.sort();
+        match self.post_order(&colors) {
+            Err(GraphError::Cycle(cycle)) => Some(cycle),
+            _ => None,
+        }
+    }
+
+    // Number of bags inside a bag of `color`, not counting itself
+    pub fn count_inside(&self, color: &str) -> Result<u128, GraphError> {
+        let mut counts: HashMap<&str, u128> = HashMap::new();
+        for c in self.post_order(&[color])? {
+            let total = self.rules[c].iter()
+                .try_fold(0u128, |acc, (inner, n)| {
+                    counts[inner.as_str()].checked_add(1)?
+                        .checked_mul(*n as u128)?
+                        .checked_add(acc)
+                })
+                .ok_or_else(|| GraphError::Overflow { color: c.to_string() })?;
+            counts.insert(c, total);
+        }
+
+        Ok(counts[color])
+    }
+
+    // Every color that can eventually contain a bag of `color`
+    pub fn containers(&self, color: &str) -> Result<HashSet<&str>, GraphError> {
+        self.contents(color)?;
+
+        let mut to_check = vec![color];
+        let mut result = HashSet::new();
+        while let Some(current) = to_check.pop() {
+            for c in self.inverted.get(current).into_iter().flatten() {
+                if result.insert(c.as_str()) {
+                    to_check.push(c);
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    // Every color that can eventually contain `color`, each with the chain of
+    // bags from that color down to `color`, sorted by color
+    pub fn containers_with_paths<'a>(&'a self, color: &'a str) -> Result<Vec<(&'a str, Vec<&'a str>)>, GraphError> {
+        self.contents(color)?;
+
+        // Breadth first so every witness is one of the shortest chains
+        let mut parent: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(color);
+        while let Some(current) = queue.pop_front() {
+            for c in self.inverted.get(current).into_iter().flatten() {
+                if c != color && !parent.contains_key(c.as_str()) {
+                    parent.insert(c, current);
+                    queue.push_back(c);
+                }
+            }
+        }
+
+        let mut result = parent.keys()
+            .map(|container| {
+                let mut path = vec![*container];
+                while let Some(next) = parent.get(path.last().unwrap()) {
+                    path.push(next);
+                }
+                (*container, path)
+            })
+            .collect::<Vec<_>>();
+        result.sort();
+        Ok(result)
+    }
+
+    // Every color that a bag of `color` can eventually contain
+    pub fn descendants(&self, color: &str) -> Result<HashSet<&str>, GraphError> {
+        let mut to_check = vec![self.contents(color)?];
+        let mut result = HashSet::new();
+        while let Some(contents) = to_check.pop() {
+            for (c, _) in contents {
+                if result.insert(c.as_str()) {
+                    to_check.push(&self.rules[c]);
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    // Levels of bags inside `color`; 0 for a bag that holds nothing
+    pub fn depth(&self, color: &str) -> Result<usize, GraphError> {
+        let mut depths: HashMap<&str, usize> = HashMap::new();
+        for c in self.post_order(&[color])? {
+            let depth = self.rules[c].iter()
+                .map(|(inner, _)| depths[inner.as_str()] + 1)
+                .max()
+                .unwrap_or(0);
+            depths.insert(c, depth);
+        }
+
+        Ok(depths[color])
+    }
+
+    // The full expansion of `color`, with the number of each bag per parent bag
+    pub fn tree(&self, color: &str) -> Result<Tree, GraphError> {
+        self.post_order(&[color])?;
+
+        // Built bottom-up with an explicit stack of (color, count, next child, children)
+        let mut stack: Vec<(&str, usize, usize, Vec<Tree>)> = vec![(color, 1, 0, vec![])];
+        loop {
+            let top = stack.last_mut().unwrap();
+            match self.rules[top.0].get(top.2) {
+                Some((child, n)) => {
+                    top.2 += 1;
+                    stack.push((child, *n, 0, vec![]));
+                },
+                None => {
+                    let (color, count, _, children) = stack.pop().unwrap();
+                    let node = Tree { color: color.to_string(), count, children };
+                    match stack.last_mut() {
+                        Some(parent) => parent.3.push(node),
+                        None => return Ok(node),
+                    }
+                },
+            }
+        }
+    }
+
+    // Colors that no other bag can contain, sorted
+    pub fn top_level(&self) -> Vec<&str> {
+        let mut colors = self.rules.keys()
+            .filter(|c| !self.inverted.contains_key(*c))
+            .map(|c| c.as_str())
+            .collect::<Vec<_>>();
+        colors.sort();
+        colors
+    }
+}
+
@@ -1,107 +1,148 @@
 #[macro_use] extern crate lazy_static;
 
-use regex::Regex;
-use std::collections::HashMap;
-use std::collections::HashSet;
+mod export;
+mod graph;
+mod parser;
+
+use graph::BagGraph;
+use graph::GraphError;
+use graph::Rules;
+use parser::parse_rule;
+use parser::write_rule;
+use parser::write_rules;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::fs::File;
 
-fn parse_rule(line: String) -> (String, Vec<(String, usize)>) {
-    lazy_static! {
-        static ref KEY: Regex = Regex::new(r"(.*) bags contain (.*)").unwrap();
-        static ref CONTENTS: Regex = Regex::new(r"(\d+)\s*([^,]+) bags?,?(.*)").unwrap();
-    }
-
-    let c = KEY.captures(&line).unwrap();
-    let key = c.get(1).unwrap().as_str().to_string();
-
-    let mut s = c.get(2).unwrap().as_str();
-    let mut contents = vec![];
-    loop {
-        let c = CONTENTS.captures(s);
-        match c {
-            None => break,
-            Some(caps) => {
-                s = caps.get(3).unwrap().as_str();
-                let count = caps.get(1).unwrap().as_str().parse::<usize>().unwrap();
-                let color = caps.get(2).unwrap().as_str().to_string();
-                contents.push((color, count));
-            }
+// Line numbers in the errors are 1-based
+fn read_input() -> Result<Rules, Vec<(usize, String)>> {
+    let input_file = File::open("src/input.txt").unwrap();
+    let reader = BufReader::new(input_file);
+    let mut rules = Rules::new();
+    let mut errors = vec![];
+    for (i, l) in reader.lines().map(|x|x.unwrap()).enumerate() {
+        match parse_rule(&l) {
+            Ok((color, contents)) => {
+                if rules.contains_key(&color) {
+                    errors.push((i + 1, format!("second rule for {:?}", color)));
+                }
+                rules.insert(color, contents);
+            },
+            Err(e) => errors.push((i + 1, e.to_string())),
         }
     }
 
-    (key, contents)
+    if errors.is_empty() { Ok(rules) } else { Err(errors) }
 }
 
-fn invert_rules(rules: &HashMap<String, Vec<(String, usize)>>) -> HashMap<String, Vec<String>> {
-    rules.iter()
-        .flat_map(|rule| rule.1.iter()
-                  .map(|c| (c.0.clone(), rule.0.clone()))
-                  .collect::<Vec<_>>())
-        .fold(HashMap::new(), |mut acc, (c1, c2)| {
-            (*acc.entry(c1).or_insert(Vec::new())).push(c2);
-            acc
-        })
-}
-
-fn read_input() -> HashMap<String, Vec<(String, usize)>> {
+// Every line has to parse and be written back out unchanged
+fn check_round_trip() {
     let input_file = File::open("src/input.txt").unwrap();
     let reader = BufReader::new(input_file);
-    reader.lines()
-        .map(|x|x.unwrap())
-        .map(|l| parse_rule(l))
-        .collect()
-}
-
-fn part1(rules: &HashMap<String, Vec<(String, usize)>>,
-         inverted: &HashMap<String, Vec<String>>) {
-    let mut to_check = inverted.get("shiny gold").unwrap().clone();
-    let mut result = HashSet::new();
-    while !to_check.is_empty() {
-        let color = to_check.pop().unwrap();
-        if inverted.contains_key(&color) {
-            for c in inverted.get(&color).unwrap() {
-                if !result.contains(c) {
-                    to_check.push(c.to_string());
+    let mut mismatches = 0;
+    for (i, l) in reader.lines().map(|x|x.unwrap()).enumerate() {
+        match parse_rule(&l) {
+            Ok((color, contents)) => {
+                let written = write_rule(&color, &contents);
+                if written != l || parse_rule(&written) != Ok((color, contents)) {
+                    println!("Line {}: {:?} was written as {:?}", i + 1, l, written);
+                    mismatches += 1;
                 }
-            }
+            },
+            Err(e) => {
+                println!("Line {}: {}", i + 1, e);
+                mismatches += 1;
+            },
         }
-
-        result.insert(color);
     }
-
-    println!("part 1: {:?}", result);
-    println!("part 1: {}", result.len());
+    println!("Round trip: {} mismatches", mismatches);
 }
 
-fn find_num(color: &str,
-            rules: &HashMap<String, Vec<(String, usize)>>,
-            lookup: &mut HashMap<String, usize>) -> usize {
-    if lookup.contains_key(color) {
-        return *lookup.get(color).unwrap();
+fn part1(graph: &BagGraph) {
+    match graph.containers("shiny gold") {
+        Ok(result) => {
+            println!("part 1: {:?}", result);
+            println!("part 1: {}", result.len());
+        },
+        Err(e) => println!("part 1: {}", e),
     }
+}
 
-    rules.get(color).unwrap().iter()
-        .map(|(c, n)| {
-            let num = find_num(c, rules, lookup);
-            lookup.insert(c.clone(), num);
-            println!("aaa: {} {} {}", c, n, num);
-            (num + 1) * n
-        })
-        .sum()
+fn part2(graph: &BagGraph) {
+    match graph.count_inside("shiny gold") {
+        Ok(n) => println!("Valid Part 2: {}", n),
+        Err(e) => println!("Part 2: {}", e),
+    }
 }
 
-fn part2(rules: &HashMap<String, Vec<(String, usize)>>) {
-    let mut lookup = HashMap::new();
-    let n = find_num("shiny gold", rules, &mut lookup);
-    println!("Valid Part 2: {}", n);
+fn query(graph: &BagGraph, command: &str, color: &str) -> Result<(), GraphError> {
+    match command {
+        "containers" => {
+            let containers = graph.containers_with_paths(color)?;
+            println!("{} colors can contain {}", containers.len(), color);
+            for (_, path) in containers {
+                println!("  {}", path.join(" -> "));
+            }
+        },
+        "inside" => println!("{} bags inside {}", graph.count_inside(color)?, color),
+        "depth" => println!("{} is nested {} levels deep", color, graph.depth(color)?),
+        "tree" => print!("{}", graph.tree(color)?.render()),
+        "dot" => print!("{}", export::to_dot(graph, Some(color).filter(|c| !c.is_empty()))?),
+        "json" => print!("{}", export::to_json(graph)),
+        "rules" => print!("{}", write_rules(graph.rules())),
+        "top" => {
+            for c in graph.top_level() {
+                println!("{}", c);
+            }
+        },
+        _ => println!("Unknown query {:?}, expected containers, inside, depth, tree, top, dot, json or rules", command),
+    }
+    Ok(())
 }
 
 fn main() {
-    println!("Reading input");
-    let rules = read_input();
-    let inverted = invert_rules(&rules);
-    part1(&rules, &inverted);
-    part2(&rules);
+    // `<containers|inside|depth|tree> <color>` or `top` answers a single query,
+    // e.g. `cargo run -- tree shiny gold`. `dot [color]` and `json` export the rules,
+    // `rules` writes them back out in canonical form and `check` round-trips the input.
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.is_empty() {
+        println!("Reading input");
+    }
+
+    if args.first().map(|a| a.as_str()) == Some("check") {
+        check_round_trip();
+        return;
+    }
+
+    let rules = match read_input() {
+        Ok(rules) => rules,
+        Err(errors) => {
+            for (line, e) in errors {
+                println!("Line {}: {}", line, e);
+            }
+            return;
+        }
+    };
+
+    let graph = match BagGraph::new(rules) {
+        Ok(graph) => graph,
+        Err(e) => {
+            println!("Bad rules: {}", e);
+            return;
+        }
+    };
+
+    if let Some((command, color)) = args.split_first() {
+        if let Err(e) = query(&graph, command, &color.join(" ")) {
+            println!("{}", e);
+        }
+        return;
+    }
+
+    if let Some(cycle) = graph.find_cycle() {
+        println!("Warning: rules contain a cycle: {}", cycle.join(" -> "));
+    }
+
+    part1(&graph);
+    part2(&graph);
 }
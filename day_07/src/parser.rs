@@ -0,0 +1,184 @@
+use crate::graph::Rules;
+use regex::Regex;
+use std::fmt;
+
+// Grammar for a rule, one per line:
+//   rule     = color " bags contain " contents "."
+//   contents = "no other bags" | item { ", " item }
+//   item     = count " " color " bag"    when count is 1
+//            | count " " color " bags"   otherwise
+//   color    = word { " " word }, words of lowercase letters
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    Malformed(String),
+    MissingPeriod,
+    BadItem(String),
+    ZeroCount(String),
+    WrongPlural { item: String, count: usize },
+    DuplicateContent(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Malformed(line) =>
+                write!(f, "expected \"<color> bags contain <contents>.\", found {:?}", line),
+            ParseError::MissingPeriod => write!(f, "missing trailing period"),
+            ParseError::BadItem(item) =>
+                write!(f, "expected \"<count> <color> bag(s)\" or \"no other bags\", found {:?}", item),
+            ParseError::ZeroCount(item) => write!(f, "zero count in {:?}", item),
+            ParseError::WrongPlural { item, count } =>
+                write!(f, "{:?} should say {:?}", item, if *count == 1 { "bag" } else { "bags" }),
+            ParseError::DuplicateContent(color) => write!(f, "{:?} listed more than once", color),
+        }
+    }
+}
+
+pub fn parse_rule(line: &str) -> Result<(String, Vec<(String, usize)>), ParseError> {
+    lazy_static! {
+        static ref RULE: Regex = Regex::new(r"^([a-z]+(?: [a-z]+)*) bags contain (.+)$").unwrap();
+        static ref ITEM: Regex = Regex::new(r"^(\d+) ([a-z]+(?: [a-z]+)*) (bags?)$").unwrap();
+    }
+
+    let c = RULE.captures(line).ok_or_else(|| ParseError::Malformed(line.to_string()))?;
+    let key = c.get(1).unwrap().as_str().to_string();
+    let contents = c.get(2).unwrap().as_str()
+        .strip_suffix('.')
+        .ok_or(ParseError::MissingPeriod)?;
+
+    if contents == "no other bags" {
+        return Ok((key, vec![]));
+    }
+
+    let mut result: Vec<(String, usize)> = vec![];
+    for item in contents.split(", ") {
+        let caps = ITEM.captures(item).ok_or_else(|| ParseError::BadItem(item.to_string()))?;
+        let count = caps.get(1).unwrap().as_str().parse::<usize>()
+            .map_err(|_| ParseError::BadItem(item.to_string()))?;
+        let color = caps.get(2).unwrap().as_str().to_string();
+
+        if count == 0 {
+            return Err(ParseError::ZeroCount(item.to_string()));
+        }
+        if (count == 1) != (caps.get(3).unwrap().as_str() == "bag") {
+            return Err(ParseError::WrongPlural { item: item.to_string(), count });
+        }
+        if result.iter().any(|(c, _)| *c == color) {
+            return Err(ParseError::DuplicateContent(color));
+        }
+        result.push((color, count));
+    }
+
+    Ok((key, result))
+}
+
+pub fn write_rule(color: &str, contents: &[(String, usize)]) -> String {
+    if contents.is_empty() {
+        return format!("{} bags contain no other bags.", color);
+    }
+
+    let items = contents.iter()
+        .map(|(c, n)| format!("{} {} {}", n, c, if *n == 1 { "bag" } else { "bags" }))
+        .collect::<Vec<_>>();
+    format!("{} bags contain {}.", color, items.join(", "))
+}
+
+// Canonical text for a rule set: one rule per line, sorted by color
+pub fn write_rules(rules: &Rules) -> String {
+    let mut colors = rules.keys().collect::<Vec<_>>();
+    colors.sort();
+    colors.iter()
+        .map(|c| write_rule(c, &rules[*c]) + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(line: &str) {
+        let (color, contents) = parse_rule(line).unwrap();
+        assert_eq!(write_rule(&color, &contents), line);
+    }
+
+    #[test]
+    fn parses_singular_and_plural_items() {
+        assert_eq!(parse_rule("light red bags contain 1 bright white bag, 2 muted yellow bags."),
+                   Ok(("light red".to_string(),
+                       vec![("bright white".to_string(), 1), ("muted yellow".to_string(), 2)])));
+    }
+
+    #[test]
+    fn parses_no_other_bags() {
+        assert_eq!(parse_rule("faded blue bags contain no other bags."),
+                   Ok(("faded blue".to_string(), vec![])));
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip("light red bags contain 1 bright white bag, 2 muted yellow bags.");
+        round_trip("bright white bags contain 1 shiny gold bag.");
+        round_trip("muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.");
+        round_trip("faded blue bags contain no other bags.");
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        assert_eq!(parse_rule("light red bags hold 1 bright white bag."),
+                   Err(ParseError::Malformed("light red bags hold 1 bright white bag.".to_string())));
+        assert_eq!(parse_rule("Light red bags contain no other bags."),
+                   Err(ParseError::Malformed("Light red bags contain no other bags.".to_string())));
+    }
+
+    #[test]
+    fn rejects_missing_period() {
+        assert_eq!(parse_rule("faded blue bags contain no other bags"), Err(ParseError::MissingPeriod));
+        assert_eq!(parse_rule("light red bags contain 1 bright white bag"), Err(ParseError::MissingPeriod));
+    }
+
+    #[test]
+    fn rejects_bad_items() {
+        assert_eq!(parse_rule("light red bags contain bright white bags."),
+                   Err(ParseError::BadItem("bright white bags".to_string())));
+        assert_eq!(parse_rule("light red bags contain 1 bright white bag,2 muted yellow bags."),
+                   Err(ParseError::BadItem("1 bright white bag,2 muted yellow bags".to_string())));
+        assert_eq!(parse_rule("light red bags contain 99999999999999999999999 bright white bags."),
+                   Err(ParseError::BadItem("99999999999999999999999 bright white bags".to_string())));
+    }
+
+    #[test]
+    fn rejects_zero_count() {
+        assert_eq!(parse_rule("light red bags contain 0 bright white bags."),
+                   Err(ParseError::ZeroCount("0 bright white bags".to_string())));
+    }
+
+    #[test]
+    fn rejects_wrong_plural() {
+        assert_eq!(parse_rule("light red bags contain 1 bright white bags."),
+                   Err(ParseError::WrongPlural { item: "1 bright white bags".to_string(), count: 1 }));
+        assert_eq!(parse_rule("light red bags contain 2 bright white bag."),
+                   Err(ParseError::WrongPlural { item: "2 bright white bag".to_string(), count: 2 }));
+    }
+
+    #[test]
+    fn rejects_duplicate_content() {
+        assert_eq!(parse_rule("light red bags contain 1 bright white bag, 2 bright white bags."),
+                   Err(ParseError::DuplicateContent("bright white".to_string())));
+    }
+
+    #[test]
+    fn writes_rules_sorted_by_color() {
+        let rules = [
+            "muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.",
+            "faded blue bags contain no other bags.",
+            "shiny gold bags contain 1 faded blue bag.",
+        ].iter()
+            .map(|l| parse_rule(l).unwrap())
+            .collect::<Rules>();
+
+        assert_eq!(write_rules(&rules),
+                   "faded blue bags contain no other bags.\n\
+                    muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.\n\
+                    shiny gold bags contain 1 faded blue bag.\n");
+    }
+}
@@ -0,0 +1,243 @@
+use crate::vm::Opcode;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+
+// Source syntax, one statement per line, `;` or `#` starting a comment:
+//   .const NAME value      named constant, usable as an acc operand or jump offset
+//   label:                 names the address of the next instruction
+//   label: jmp +1          a label and an instruction on the same line
+//   acc +6 / acc -NAME     instructions with literal or constant operands
+//   jmp label / nop label  jump targets as labels, resolved to relative offsets
+// The plain puzzle input is valid source.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    Syntax(String),
+    UnknownMnemonic(String),
+    BadOperand(String),
+    UndefinedSymbol(String),
+    DuplicateSymbol(String),
+    LabelNotAllowed(String),
+}
+
+// Line numbers are 1-based
+#[derive(Debug, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            AsmErrorKind::Syntax(s) => write!(f, "can't parse {:?}", s),
+            AsmErrorKind::UnknownMnemonic(m) => write!(f, "unknown mnemonic {:?}", m),
+            AsmErrorKind::BadOperand(o) => write!(f, "bad operand {:?}", o),
+            AsmErrorKind::UndefinedSymbol(s) => write!(f, "undefined symbol {:?}", s),
+            AsmErrorKind::DuplicateSymbol(s) => write!(f, "{:?} is already defined", s),
+            AsmErrorKind::LabelNotAllowed(s) => write!(f, "label {:?} can only be a jmp or nop target", s),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Assembly {
+    pub program: Vec<Opcode>,
+    // Address to label names in source order, including labels at the end of
+    // the program
+    pub labels: BTreeMap<usize, Vec<String>>,
+    // Address of each jmp or nop to the label its operand named
+    pub references: BTreeMap<usize, String>,
+}
+
+enum Symbol {
+    Label(usize),
+    Constant(isize),
+}
+
+struct Statement<'a> {
+    line: usize,
+    address: usize,
+    mnemonic: &'a str,
+    operand: Option<&'a str>,
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split([';', '#']).next().unwrap().trim()
+}
+
+// The instruction, and the label its operand named if it was one
+fn resolve<'a>(statement: &Statement<'a>, symbols: &HashMap<String, Symbol>) -> Result<(Opcode, Option<&'a str>), AsmErrorKind> {
+    lazy_static! {
+        static ref NUMBER: Regex = Regex::new(r"^[+-]?\d+$").unwrap();
+        static ref NAME: Regex = Regex::new(r"^([+-]?)([A-Za-z_]\w*)$").unwrap();
+    }
+
+    Opcode::from_mnemonic(statement.mnemonic, 0)
+        .ok_or_else(|| AsmErrorKind::UnknownMnemonic(statement.mnemonic.to_string()))?;
+    let operand = statement.operand
+        .ok_or_else(|| AsmErrorKind::BadOperand(String::new()))?;
+    let relative = statement.mnemonic != "acc";
+
+    let mut label = None;
+    let value = if NUMBER.is_match(operand) {
+        operand.parse::<isize>().map_err(|_| AsmErrorKind::BadOperand(operand.to_string()))?
+    } else if let Some(caps) = NAME.captures(operand) {
+        let sign = caps.get(1).unwrap().as_str();
+        let name = caps.get(2).unwrap().as_str();
+        match symbols.get(name) {
+            Some(Symbol::Constant(x)) if sign == "-" =>
+                x.checked_neg().ok_or_else(|| AsmErrorKind::BadOperand(operand.to_string()))?,
+            Some(Symbol::Constant(x)) => *x,
+            Some(Symbol::Label(address)) if relative && sign.is_empty() => {
+                label = Some(name);
+                *address as isize - statement.address as isize
+            },
+            Some(Symbol::Label(_)) => return Err(AsmErrorKind::LabelNotAllowed(name.to_string())),
+            None => return Err(AsmErrorKind::UndefinedSymbol(name.to_string())),
+        }
+    } else {
+        return Err(AsmErrorKind::BadOperand(operand.to_string()));
+    };
+
+    Ok((Opcode::from_mnemonic(statement.mnemonic, value).unwrap(), label))
+}
+
+// Collects every error rather than stopping at the first
+pub fn assemble(source: &str) -> Result<Assembly, Vec<AsmError>> {
+    lazy_static! {
+        static ref CONST: Regex = Regex::new(r"^\.const\s+([A-Za-z_]\w*)\s+([+-]?\d+)$").unwrap();
+        static ref STATEMENT: Regex =
+            Regex::new(r"^(?:([A-Za-z_]\w*):)?\s*(?:([a-z]+)(?:\s+(\S+))?)?$").unwrap();
+    }
+
+    let mut errors = vec![];
+    let mut symbols = HashMap::new();
+    let mut labels = BTreeMap::new();
+    let mut statements = vec![];
+
+    // First pass: addresses of labels, constants and statements to resolve
+    for (i, l) in source.lines().enumerate() {
+        let line = i + 1;
+        let text = strip_comment(l);
+        if text.is_empty() { continue; }
+
+        if let Some(caps) = CONST.captures(text) {
+            let name = caps.get(1).unwrap().as_str();
+            match caps.get(2).unwrap().as_str().parse::<isize>() {
+                Ok(value) => {
+                    if symbols.insert(name.to_string(), Symbol::Constant(value)).is_some() {
+                        errors.push(AsmError { line, kind: AsmErrorKind::DuplicateSymbol(name.to_string()) });
+                    }
+                },
+                Err(_) => errors.push(AsmError { line, kind: AsmErrorKind::BadOperand(text.to_string()) }),
+            }
+            continue;
+        }
+
+        let caps = match STATEMENT.captures(text) {
+            Some(caps) => caps,
+            None => {
+                errors.push(AsmError { line, kind: AsmErrorKind::Syntax(text.to_string()) });
+                continue;
+            },
+        };
+
+        let address = statements.len();
+        if let Some(label) = caps.get(1) {
+            let name = label.as_str();
+            if symbols.insert(name.to_string(), Symbol::Label(address)).is_some() {
+                errors.push(AsmError { line, kind: AsmErrorKind::DuplicateSymbol(name.to_string()) });
+            }
+            labels.entry(address).or_insert_with(Vec::new).push(name.to_string());
+        }
+
+        if let Some(mnemonic) = caps.get(2) {
+            statements.push(Statement {
+                line,
+                address,
+                mnemonic: mnemonic.as_str(),
+                operand: caps.get(3).map(|o| o.as_str()),
+            });
+        }
+    }
+
+    // Second pass: operands
+    let mut program = vec![];
+    let mut references = BTreeMap::new();
+    for s in &statements {
+        match resolve(s, &symbols) {
+            Ok((op, label)) => {
+                if let Some(label) = label {
+                    references.insert(s.address, label.to_string());
+                }
+                program.push(op);
+            },
+            Err(kind) => errors.push(AsmError { line: s.line, kind }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(Assembly { program, labels, references })
+    } else {
+        errors.sort_by_key(|e| e.line);
+        Err(errors)
+    }
+}
+
+// A listing that assembles back to the same program. Every label from the
+// source is kept, and a jmp uses the label its source named. Other jmp
+// targets inside the program get a generated `L<address>` label, with a
+// suffix if the source already uses that name. Every instruction is annotated
+// with its address and target.
+pub fn disassemble(assembly: &Assembly) -> String {
+    let program = &assembly.program;
+    let target = |address: usize, op: &Opcode| -> Option<usize> {
+        let t = (address as isize).checked_add(op.operand())?;
+        if t >= 0 && t <= program.len() as isize { Some(t as usize) } else { None }
+    };
+
+    let mut names = assembly.labels.clone();
+    let mut taken = names.values().flatten().cloned().collect::<HashSet<_>>();
+    for (address, op) in program.iter().enumerate() {
+        if let (Opcode::Jmp(_), Some(t)) = (op, target(address, op)) {
+            names.entry(t).or_insert_with(|| {
+                let mut name = format!("L{}", t);
+                let mut suffix = 0;
+                while taken.contains(&name) {
+                    suffix += 1;
+                    name = format!("L{}_{}", t, suffix);
+                }
+                taken.insert(name.clone());
+                vec![name]
+            });
+        }
+    }
+
+    let mut out = String::new();
+    for address in 0..=program.len() {
+        for name in names.get(&address).into_iter().flatten() {
+            out.push_str(&format!("{}:\n", name));
+        }
+
+        let op = match program.get(address) {
+            Some(op) => op,
+            None => break,
+        };
+        let (text, note) = match (op, target(address, op)) {
+            (Opcode::Acc(_), _) => (op.to_string(), format!("{}", address)),
+            (Opcode::Jmp(_), Some(t)) => {
+                let name = assembly.references.get(&address).unwrap_or(&names[&t][0]);
+                (format!("jmp {}", name), format!("{} -> {}", address, t))
+            },
+            (Opcode::Jmp(_), None) => (op.to_string(), format!("{} -> out of bounds", address)),
+            (Opcode::Nop(_), Some(t)) => (op.to_string(), format!("{} (-> {} as jmp)", address, t)),
+            (Opcode::Nop(_), None) => (op.to_string(), format!("{}", address)),
+        };
+        out.push_str(&format!("    {:<20} ; {}\n", text, note));
+    }
+    out
+}
@@ -0,0 +1,256 @@
+use crate::vm::Machine;
+use crate::vm::Opcode;
+use crate::vm::Outcome;
+use std::io::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    Address(usize),
+    Mnemonic(String),
+}
+
+impl Breakpoint {
+    // A number is an address, anything else has to be an opcode mnemonic
+    pub fn parse(s: &str) -> Option<Breakpoint> {
+        match s.parse::<usize>() {
+            Ok(address) => Some(Breakpoint::Address(address)),
+            Err(_) => Opcode::from_mnemonic(s, 0).map(|_| Breakpoint::Mnemonic(s.to_string())),
+        }
+    }
+
+    fn matches(&self, address: usize, op: &Opcode) -> bool {
+        match self {
+            Breakpoint::Address(a) => *a == address,
+            Breakpoint::Mnemonic(m) => m == op.mnemonic(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TraceEntry {
+    pub address: usize,
+    pub op: Opcode,
+    pub acc_before: isize,
+    pub acc_after: isize,
+}
+
+// Why `cont` gave control back
+#[derive(Debug, PartialEq, Eq)]
+pub enum Stop {
+    Breakpoint(usize),
+    AccChanged { from: isize, to: isize },
+    Halted(Outcome),
+}
+
+#[derive(Debug)]
+pub struct LoopInfo {
+    // The address that was about to run for the second time
+    pub address: usize,
+    // The instructions executed from its first run up to the repeat
+    pub cycle: Vec<TraceEntry>,
+}
+
+#[derive(Debug)]
+pub struct Debugger<'a> {
+    machine: Machine<'a>,
+    breakpoints: Vec<Breakpoint>,
+    watch_acc: bool,
+    trace: Vec<TraceEntry>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(program: &'a [Opcode]) -> Debugger<'a> {
+        Debugger { machine: Machine::new(program), breakpoints: vec![], watch_acc: false, trace: vec![] }
+    }
+
+    pub fn machine(&self) -> &Machine<'a> {
+        &self.machine
+    }
+
+    pub fn add_breakpoint(&mut self, b: Breakpoint) {
+        if !self.breakpoints.contains(&b) {
+            self.breakpoints.push(b);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, b: &Breakpoint) -> bool {
+        let len = self.breakpoints.len();
+        self.breakpoints.retain(|x| x != b);
+        self.breakpoints.len() != len
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn watch_acc(&self) -> bool {
+        self.watch_acc
+    }
+
+    pub fn set_watch_acc(&mut self, watch: bool) {
+        self.watch_acc = watch;
+    }
+
+    pub fn trace(&self) -> &[TraceEntry] {
+        &self.trace
+    }
+
+    pub fn step(&mut self) -> Outcome {
+        let before = self.machine.state();
+        let outcome = self.machine.step();
+        if outcome == Outcome::Running {
+            let address = before.ip as usize;
+            self.trace.push(TraceEntry {
+                address,
+                op: self.machine.program()[address],
+                acc_before: before.acc,
+                acc_after: self.machine.state().acc,
+            });
+        }
+        outcome
+    }
+
+    // Runs until a breakpoint is reached, the watched accumulator changes or the
+    // machine stops. Breakpoints stop before their instruction runs, and the
+    // instruction at the current address never stops it again.
+    pub fn cont(&mut self) -> Stop {
+        let mut first = true;
+        loop {
+            let outcome = self.machine.check();
+            if outcome != Outcome::Running {
+                return Stop::Halted(outcome);
+            }
+
+            let address = self.machine.state().ip as usize;
+            let op = self.machine.program()[address];
+            if !first && self.breakpoints.iter().any(|b| b.matches(address, &op)) {
+                return Stop::Breakpoint(address);
+            }
+            first = false;
+
+            self.step();
+            let last = self.trace.last().unwrap();
+            if self.watch_acc && last.acc_before != last.acc_after {
+                return Stop::AccChanged { from: last.acc_before, to: last.acc_after };
+            }
+        }
+    }
+
+    pub fn loop_info(&self) -> Option<LoopInfo> {
+        if self.machine.check() != Outcome::Looped {
+            return None;
+        }
+
+        let address = self.machine.state().ip as usize;
+        let start = self.trace.iter().position(|t| t.address == address)?;
+        Some(LoopInfo { address, cycle: self.trace[start..].to_vec() })
+    }
+}
+
+fn print_entry(t: &TraceEntry) {
+    println!("{:>5}: {:<10} acc {} -> {}", t.address, t.op.to_string(), t.acc_before, t.acc_after);
+}
+
+fn print_state(debugger: &Debugger) {
+    let state = debugger.machine().state();
+    let op = debugger.machine().program().get(state.ip as usize);
+    match op {
+        Some(op) => println!("ip {}, acc {}, next: {}", state.ip, state.acc, op),
+        None => println!("ip {}, acc {}", state.ip, state.acc),
+    }
+}
+
+fn print_outcome(debugger: &Debugger, outcome: Outcome) {
+    println!("Machine stopped: {:?}", outcome);
+    if let Some(info) = debugger.loop_info() {
+        println!("Loop: address {} would run a second time, cycle of {} instructions:",
+                 info.address, info.cycle.len());
+        for t in &info.cycle {
+            print_entry(t);
+        }
+    }
+}
+
+const HELP: &str = "\
+commands:
+  s [n]          step n instructions (default 1)
+  c              continue to the next breakpoint, watch or stop
+  b <addr|op>    break at an address or on an opcode (acc, jmp, nop)
+  d <addr|op>    delete a breakpoint
+  w              toggle watching the accumulator
+  t [n]          show the last n executed instructions (default 10)
+  l              list the program around the instruction pointer
+  p              print the machine state
+  q              quit";
+
+// Reads commands from stdin until `q` or end of input
+pub fn run_interactive(program: &[Opcode]) {
+    let mut debugger = Debugger::new(program);
+    println!("{}", HELP);
+    print_state(&debugger);
+
+    let stdin = std::io::stdin();
+    loop {
+        print!("(dbg) ");
+        std::io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let arg = words.get(1).copied();
+
+        match words.first().copied() {
+            Some("s") => {
+                let n = arg.and_then(|a| a.parse().ok()).unwrap_or(1);
+                for _ in 0..n {
+                    let outcome = debugger.step();
+                    if outcome != Outcome::Running {
+                        print_outcome(&debugger, outcome);
+                        break;
+                    }
+                    print_entry(debugger.trace().last().unwrap());
+                }
+            },
+            Some("c") => match debugger.cont() {
+                Stop::Breakpoint(address) => println!("Breakpoint at {}", address),
+                Stop::AccChanged { from, to } => println!("acc changed {} -> {}", from, to),
+                Stop::Halted(outcome) => print_outcome(&debugger, outcome),
+            },
+            Some("b") => match arg.map(|a| (a, Breakpoint::parse(a))) {
+                Some((_, Some(b))) => debugger.add_breakpoint(b),
+                Some((a, None)) => println!("Bad breakpoint {:?}, expected an address, acc, jmp or nop", a),
+                None => println!("Breakpoints: {:?}", debugger.breakpoints()),
+            },
+            Some("d") => match arg.and_then(Breakpoint::parse) {
+                Some(b) if debugger.remove_breakpoint(&b) => {},
+                _ => println!("No such breakpoint"),
+            },
+            Some("w") => {
+                let watch = !debugger.watch_acc();
+                debugger.set_watch_acc(watch);
+                println!("Watching acc: {}", watch);
+            },
+            Some("t") => {
+                let n = arg.and_then(|a| a.parse().ok()).unwrap_or(10);
+                let trace = debugger.trace();
+                for t in &trace[trace.len().saturating_sub(n)..] {
+                    print_entry(t);
+                }
+            },
+            Some("l") => {
+                let ip = debugger.machine().state().ip;
+                for (i, op) in program.iter().enumerate() {
+                    if (i as isize - ip).abs() <= 5 {
+                        println!("{} {:>5}: {}", if i as isize == ip { "=>" } else { "  " }, i, op);
+                    }
+                }
+            },
+            Some("p") => print_state(&debugger),
+            Some("q") => break,
+            Some(_) => println!("{}", HELP),
+            None => {},
+        }
+    }
+}
@@ -1,91 +1,142 @@
 #[macro_use] extern crate lazy_static;
 
-use regex::Regex;
-use std::collections::HashMap;
-use std::collections::HashSet;
-use std::io::prelude::*;
-use std::io::BufReader;
-use std::fs::File;
+mod asm;
+mod debugger;
+mod repair;
+mod vm;
 
-#[derive(Debug, PartialEq, Eq)]
-enum Opcode {
-    Acc(isize),
-    Jmp(isize),
-    Nop(isize),
-}
-
-impl Opcode {
-    fn new(line: &str) -> Opcode {
-        lazy_static! {
-            static ref PARSER: Regex = Regex::new(r"^(...)\s*([+-]\d*)\s*$").unwrap();
-        }
+use asm::Assembly;
+use vm::Machine;
+use vm::Opcode;
 
-        let cap = PARSER.captures(line).unwrap();
-        match cap.get(1).unwrap().as_str() {
-            "acc" => Opcode::Acc(cap.get(2).unwrap().as_str().parse().unwrap()),
-            "jmp" => Opcode::Jmp(cap.get(2).unwrap().as_str().parse().unwrap()),
-            _ => Opcode::Nop(cap.get(2).unwrap().as_str().parse().unwrap()),
-        }
-    }
+fn read_source(path: &str) -> Result<Assembly, Vec<String>> {
+    let source = std::fs::read_to_string(path).map_err(|e| vec![format!("{}: {}", path, e)])?;
+    asm::assemble(&source).map_err(|errors| errors.iter().map(|e| e.to_string()).collect())
 }
 
-fn read_input() -> Vec<Opcode> {
-    let input_file = File::open("src/input.txt").unwrap();
-    let reader = BufReader::new(input_file);
-    reader.lines()
-        .map(|x|x.unwrap())
-        .map(|l| Opcode::new(&l))
-        .collect()
+fn part1(opcodes: &[Opcode]) {
+    let mut machine = Machine::new(opcodes);
+    let outcome = machine.run();
+    println!("Part 1: {:?}, acc {}", outcome, machine.state().acc);
 }
 
-fn part1(opcodes: &Vec<Opcode>) -> Result<isize, &str> {
-    let mut acc = 0;
-    let mut i : isize = 0;
-    let mut visited = HashSet::new();
+fn part2(opcodes: &[Opcode]) {
+    if repair::terminating(opcodes)[0] {
+        let mut machine = Machine::new(opcodes);
+        machine.run();
+        println!("Part 2: the program already terminates, acc {}", machine.state().acc);
+        return;
+    }
 
-    loop {
-        if i == opcodes.len() as isize {
-            return Ok(acc);
-        }
+    match repair::repair(opcodes) {
+        Some(r) => println!("Part 2: flip {}: {} -> {}, acc {}", r.index, r.original, r.patched[r.index], r.acc),
+        None => println!("Part 2: no single flip makes the program terminate"),
+    }
+}
 
-        if i > opcodes.len() as isize || i < 0 {
-            return Err("out of range");
-        }
+// The repaired program, or with `diff` only the changed line as `-` and `+` lines
+fn print_patched(opcodes: &[Opcode], diff: bool) {
+    if repair::terminating(opcodes)[0] {
+        println!("The program already terminates");
+        return;
+    }
 
-        if visited.contains(&i) {
-            return Err("looped");
+    let r = match repair::repair(opcodes) {
+        Some(r) => r,
+        None => {
+            println!("No single flip repairs the program");
+            return;
         }
+    };
 
-        visited.insert(i);
-        match opcodes[i as usize] {
-            Opcode::Acc(x) => { acc += x; i+= 1; },
-            Opcode::Jmp(x) => { i += x; },
-            Opcode::Nop(_) => { i += 1;} ,
+    if diff {
+        println!("@@ {} @@", r.index + 1);
+        println!("-{}", r.original);
+        println!("+{}", r.patched[r.index]);
+    } else {
+        for op in &r.patched {
+            println!("{}", op);
         }
     }
 }
 
-fn flip_opcode(op: &Opcode) -> Opcode {
-    match op {
-        Opcode::Acc(x) => { Opcode::Acc(*x) }
-        Opcode::Jmp(x) => { Opcode::Nop(*x) }
-        Opcode::Nop(x) => { Opcode::Jmp(*x) } ,
+// Every cheapest repair, one per line with its patches
+fn search(opcodes: &[Opcode], options: &repair::SearchOptions) {
+    let repairs = repair::repairs(opcodes, options);
+    if repairs.is_empty() {
+        println!("No repair costing at most {}", options.max_cost);
     }
-}
-
-fn part2(opcodes: &mut Vec<Opcode>) {
-    for i in 0..opcodes.len() {
-        if let Opcode::Acc(_) = opcodes[i] { continue; }
-        opcodes[i] = flip_opcode(&opcodes[i]);
-        let res = part1(&opcodes);
-        if res.is_ok() { println!("{}: acc {}", i, res.unwrap()); }
-        opcodes[i] = flip_opcode(&opcodes[i]);
+    for r in repairs {
+        let patches = r.patches.iter()
+            .map(|p| format!("{}: {} -> {}", p.index, p.original, p.replacement))
+            .collect::<Vec<_>>();
+        println!("cost {}, acc {}: {}", r.cost, r.acc, patches.join(", "));
     }
 }
 
 fn main() {
-    println!("Reading input");
-    let mut opcodes = read_input();
-    part1(&opcodes);
-    part2(&mut opcodes);
+    // `debug` starts the interactive debugger instead of solving, `disasm`
+    // prints an annotated listing and `asm <file>` assembles a source file to
+    // the plain puzzle format. `search <max cost> [--deletions]` lists every
+    // cheapest repair, allowing more than one patch. `patched [--diff]` prints
+    // the repaired program, or only the flipped line.
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let mode = args.first().map(|a| a.as_str());
+    let path = match (mode, args.get(1)) {
+        (Some("asm"), Some(path)) => path.as_str(),
+        (Some("asm"), None) => {
+            println!("Usage: asm <file>");
+            return;
+        },
+        _ => "src/input.txt",
+    };
+    let assembly = match read_source(path) {
+        Ok(assembly) => assembly,
+        Err(errors) => {
+            for e in errors {
+                println!("{}", e);
+            }
+            return;
+        }
+    };
+    let opcodes = &assembly.program;
+
+    match mode {
+        None => println!("Reading input"),
+        Some("debug") => {
+            debugger::run_interactive(opcodes);
+            return;
+        },
+        Some("disasm") => {
+            print!("{}", asm::disassemble(&assembly));
+            return;
+        },
+        Some("asm") => {
+            for op in opcodes {
+                println!("{}", op);
+            }
+            return;
+        },
+        Some("search") => {
+            match args.get(1).map(|c| c.parse::<usize>()) {
+                Some(Ok(max_cost)) => {
+                    let allow_deletions = args.iter().any(|a| a == "--deletions");
+                    search(opcodes, &repair::SearchOptions { max_cost, allow_deletions });
+                },
+                _ => println!("Usage: search <max cost> [--deletions]"),
+            }
+            return;
+        },
+        Some("patched") => {
+            print_patched(opcodes, args.iter().any(|a| a == "--diff"));
+            return;
+        },
+        Some(m) => {
+            println!("Unknown mode {:?}, expected debug, disasm, asm <file>, search <max cost> or patched", m);
+            return;
+        },
+    }
+
+    part1(opcodes);
+    part2(opcodes);
 }
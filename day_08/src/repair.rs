@@ -0,0 +1,228 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use crate::vm::Machine;
+use crate::vm::Opcode;
+use crate::vm::Outcome;
+
+// Where control goes after instruction `i`: Some(n) is the end of a program of
+// n instructions, None is out of bounds, including a jump that overflows
+pub fn successor(program: &[Opcode], i: usize, op: &Opcode) -> Option<usize> {
+    let next = match op {
+        Opcode::Jmp(x) => (i as isize).checked_add(*x)?,
+        _ => i as isize + 1,
+    };
+    if next >= 0 && next <= program.len() as isize { Some(next as usize) } else { None }
+}
+
+// The control flow graph: successor of every instruction
+pub fn control_flow(program: &[Opcode]) -> Vec<Option<usize>> {
+    program.iter()
+        .enumerate()
+        .map(|(i, op)| successor(program, i, op))
+        .collect()
+}
+
+// For every instruction, plus the end of the program at index n, whether
+// execution starting there terminates. Walks the reversed graph back from the end.
+pub fn terminating(program: &[Opcode]) -> Vec<bool> {
+    let n = program.len();
+    let mut predecessors = vec![vec![]; n + 1];
+    for (i, next) in control_flow(program).into_iter().enumerate() {
+        if let Some(next) = next {
+            predecessors[next].push(i);
+        }
+    }
+
+    let mut result = vec![false; n + 1];
+    result[n] = true;
+    let mut to_check = vec![n];
+    while let Some(i) = to_check.pop() {
+        for p in &predecessors[i] {
+            if !result[*p] {
+                result[*p] = true;
+                to_check.push(*p);
+            }
+        }
+    }
+    result
+}
+
+#[derive(Debug)]
+pub struct Repair {
+    pub index: usize,
+    pub original: Opcode,
+    pub patched: Vec<Opcode>,
+    pub acc: isize,
+}
+
+// Finds the single jmp/nop flip that makes the program terminate in O(n).
+// Every instruction on the original looping path can't reach the end, so
+// flipping one of them terminates exactly when its new successor can.
+pub fn repair(program: &[Opcode]) -> Option<Repair> {
+    let terminates = terminating(program);
+    if terminates[0] {
+        return None;
+    }
+
+    let mut machine = Machine::new(program);
+    let index = loop {
+        if machine.check() != Outcome::Running {
+            return None;
+        }
+
+        let i = machine.state().ip as usize;
+        let flipped = program[i].flipped();
+        if flipped != program[i] && successor(program, i, &flipped).is_some_and(|next| terminates[next]) {
+            break i;
+        }
+        machine.step();
+    };
+
+    let mut patched = program.to_vec();
+    patched[index] = program[index].flipped();
+    let mut machine = Machine::new(&patched);
+    let outcome = machine.run();
+    assert_eq!(outcome, Outcome::Terminated);
+    let acc = machine.state().acc;
+
+    Some(Repair { index, original: program[index], patched, acc })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions {
+    // Repairs costing more than this aren't looked for
+    pub max_cost: usize,
+    // Whether an acc may be deleted, replacing it with a nop. Both go to the
+    // next instruction, so a deletion never changes whether the program
+    // terminates and never appears in a cheapest repair.
+    pub allow_deletions: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Patch {
+    pub index: usize,
+    pub original: Opcode,
+    pub replacement: Opcode,
+}
+
+#[derive(Debug)]
+pub struct MultiRepair {
+    // Sorted by index
+    pub patches: Vec<Patch>,
+    // 1 per flip or deletion
+    pub cost: usize,
+    pub acc: isize,
+}
+
+// Every way control can leave instruction `i`, as (successor, patch to take
+// it); the unpatched way first
+fn choices(program: &[Opcode], i: usize, options: &SearchOptions) -> Vec<(usize, Option<Patch>)> {
+    let original = program[i];
+    let mut result = vec![];
+    if let Some(next) = successor(program, i, &original) {
+        result.push((next, None));
+    }
+
+    let replacement = match original {
+        Opcode::Acc(x) if options.allow_deletions => Some(Opcode::Nop(x)),
+        Opcode::Acc(_) => None,
+        op => Some(op.flipped()),
+    };
+    if let Some(replacement) = replacement {
+        if let Some(next) = successor(program, i, &replacement) {
+            result.push((next, Some(Patch { index: i, original, replacement })));
+        }
+    }
+    result
+}
+
+// Finds every cheapest set of patches that makes the program terminate, as
+// long as it costs at most `options.max_cost`. A program that already
+// terminates has a single repair with no patches.
+//
+// The patched program runs along one path from 0 to the end, so a repair is
+// a simple path where taking a patched way costs 1. Dijkstra on the reversed
+// graph gives the cheapest cost from every instruction to the end, then a
+// depth first search follows only the edges on a cheapest path, skipping
+// instructions already on the current path since executing one twice loops.
+pub fn repairs(program: &[Opcode], options: &SearchOptions) -> Vec<MultiRepair> {
+    let n = program.len();
+    let choices = (0..n).map(|i| choices(program, i, options)).collect::<Vec<_>>();
+    let edge_cost = |patch: &Option<Patch>| usize::from(patch.is_some());
+
+    let mut predecessors = vec![vec![]; n + 1];
+    for (i, c) in choices.iter().enumerate() {
+        for (next, patch) in c {
+            predecessors[*next].push((i, edge_cost(patch)));
+        }
+    }
+
+    let mut to_end = vec![usize::MAX; n + 1];
+    let mut queue = BinaryHeap::new();
+    to_end[n] = 0;
+    queue.push(Reverse((0, n)));
+    while let Some(Reverse((cost, i))) = queue.pop() {
+        if cost > to_end[i] { continue; }
+        for (p, c) in &predecessors[i] {
+            if cost + c < to_end[*p] {
+                to_end[*p] = cost + c;
+                queue.push(Reverse((cost + c, *p)));
+            }
+        }
+    }
+
+    if n == 0 || to_end[0] > options.max_cost {
+        return vec![];
+    }
+
+    // Iterative, with (instruction, next choice to try) on the stack
+    let mut result = vec![];
+    let mut on_path = vec![false; n];
+    let mut patches = vec![];
+    let mut stack = vec![(0, 0)];
+    on_path[0] = true;
+    while let Some(&(i, next)) = stack.last() {
+        let choice = match choices[i].get(next) {
+            Some(choice) => choice,
+            None => {
+                stack.pop();
+                on_path[i] = false;
+                if stack.last().is_some_and(|(p, c)| choices[*p][c - 1].1.is_some()) {
+                    patches.pop();
+                }
+                continue;
+            },
+        };
+        stack.last_mut().unwrap().1 += 1;
+
+        let (to, patch) = *choice;
+        if to_end[to] == usize::MAX || edge_cost(&patch) + to_end[to] != to_end[i] {
+            continue;
+        }
+
+        if to == n {
+            let mut found = patches.clone();
+            found.extend(patch);
+            result.push(found);
+        } else if !on_path[to] {
+            on_path[to] = true;
+            patches.extend(patch);
+            stack.push((to, 0));
+        }
+    }
+
+    result.into_iter()
+        .map(|mut patches| {
+            patches.sort_by_key(|p| p.index);
+            let mut patched = program.to_vec();
+            for p in &patches {
+                patched[p.index] = p.replacement;
+            }
+            let mut machine = Machine::new(&patched);
+            let outcome = machine.run();
+            assert_eq!(outcome, Outcome::Terminated);
+            let cost = patches.len();
+            MultiRepair { patches, cost, acc: machine.state().acc }
+        })
+        .collect()
+}
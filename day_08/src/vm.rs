@@ -0,0 +1,139 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Acc(isize),
+    Jmp(isize),
+    Nop(isize),
+}
+
+impl Opcode {
+    // None for an unknown mnemonic
+    pub fn from_mnemonic(mnemonic: &str, operand: isize) -> Option<Opcode> {
+        match mnemonic {
+            "acc" => Some(Opcode::Acc(operand)),
+            "jmp" => Some(Opcode::Jmp(operand)),
+            "nop" => Some(Opcode::Nop(operand)),
+            _ => None,
+        }
+    }
+
+    pub fn operand(&self) -> isize {
+        match self {
+            Opcode::Acc(x) | Opcode::Jmp(x) | Opcode::Nop(x) => *x,
+        }
+    }
+
+    // All of an instruction's semantics live here; a new opcode only needs a
+    // variant, an arm in `from_mnemonic` and `mnemonic`, and an arm below
+    pub fn execute(&self, state: &mut State) {
+        match self {
+            Opcode::Acc(x) => { state.acc += x; state.ip += 1; },
+            // A jump that overflows lands out of bounds
+            Opcode::Jmp(x) => { state.ip = state.ip.checked_add(*x).unwrap_or(isize::MAX); },
+            Opcode::Nop(_) => { state.ip += 1; },
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Opcode::Acc(_) => "acc",
+            Opcode::Jmp(_) => "jmp",
+            Opcode::Nop(_) => "nop",
+        }
+    }
+
+    // jmp and nop swapped, acc unchanged
+    pub fn flipped(&self) -> Opcode {
+        match self {
+            Opcode::Acc(x) => Opcode::Acc(*x),
+            Opcode::Jmp(x) => Opcode::Nop(*x),
+            Opcode::Nop(x) => Opcode::Jmp(*x),
+        }
+    }
+}
+
+// The same `acc +6` form the programs are written in
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Opcode::Acc(x) => write!(f, "acc {:+}", x),
+            Opcode::Jmp(x) => write!(f, "jmp {:+}", x),
+            Opcode::Nop(x) => write!(f, "nop {:+}", x),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct State {
+    pub ip: isize,
+    pub acc: isize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    // An instruction was executed and the machine can keep going
+    Running,
+    // The instruction pointer is right after the last instruction
+    Terminated,
+    // The next instruction has already been executed once
+    Looped,
+    // The instruction pointer is before the start or past the end
+    OutOfBounds,
+}
+
+#[derive(Debug)]
+pub struct Machine<'a> {
+    program: &'a [Opcode],
+    state: State,
+    visited: Vec<bool>,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(program: &'a [Opcode]) -> Machine<'a> {
+        Machine { program, state: State::default(), visited: vec![false; program.len()] }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn program(&self) -> &'a [Opcode] {
+        self.program
+    }
+
+    // The outcome if the machine can't go on, without executing anything
+    pub fn check(&self) -> Outcome {
+        let ip = self.state.ip;
+        if ip == self.program.len() as isize {
+            Outcome::Terminated
+        } else if ip < 0 || ip > self.program.len() as isize {
+            Outcome::OutOfBounds
+        } else if self.visited[ip as usize] {
+            Outcome::Looped
+        } else {
+            Outcome::Running
+        }
+    }
+
+    // Executes the next instruction, unless the machine has stopped
+    pub fn step(&mut self) -> Outcome {
+        let outcome = self.check();
+        if outcome == Outcome::Running {
+            let ip = self.state.ip as usize;
+            self.visited[ip] = true;
+            self.program[ip].execute(&mut self.state);
+        }
+        outcome
+    }
+
+    // Steps until the machine stops, returning why
+    pub fn run(&mut self) -> Outcome {
+        loop {
+            let outcome = self.step();
+            if outcome != Outcome::Running {
+                return outcome;
+            }
+        }
+    }
+}
@@ -1,90 +1,100 @@
-use std::collections::HashSet;
+mod ranges;
+mod validator;
+
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::fs::File;
+use validator::Validator;
 
-fn read_input() -> Vec<usize> {
+fn read_input() -> Vec<i64> {
     let input_file = File::open("src/input.txt").unwrap();
     let reader = BufReader::new(input_file);
     reader.lines()
         .map(|x|x.unwrap())
-        .map(|num|num.parse::<usize>().unwrap())
+        .map(|num|num.parse::<i64>().unwrap())
         .collect::<Vec<_>>()
 }
 
-fn valid(window: &HashSet<usize>, num: &usize) -> bool {
-    for n in window {
-        if n >= num { continue; }
-        if *num - n == *n { continue; }
-        if window.contains(&(num-n)) { return true; }
-    }
-
-    return false;
-}
-
-fn part1(input: &Vec<usize>) -> usize {
-    let window_size = 25;
-    let mut sliding_window : HashSet<usize> = input.iter().take(window_size).cloned().collect();
+const PREAMBLE: usize = 25;
 
-    for i in window_size..input.len() {
-        let n = input[i];
-        if !valid(&sliding_window, &n) {
+// The first invalid number, if any
+fn part1(input: &[i64]) -> Option<i64> {
+    let mut validator = Validator::new(PREAMBLE);
+    match input.iter().find(|n| !validator.push(**n)) {
+        Some(n) => {
             println!("Part 1: Invalid: {}", n);
-            return n;
-        }
-
-        sliding_window.remove(&input[i-window_size]);
-        sliding_window.insert(n);
+            Some(*n)
+        },
+        None => {
+            println!("Couldn't find a number!");
+            None
+        },
     }
-
-    println!("Couldn't find a number!");
-    return 0;
 }
 
-fn part2(input: &Vec<usize>, n: usize) {
-    let mut sum = input[0];
-    let mut start_index = 0;
-    let mut end_index = 0;
-
-    loop {
-        if sum == n && end_index > start_index {
-            let mut x = input.iter()
-                .skip(start_index)
-                .take(end_index-start_index+1)
-                .collect::<Vec<_>>();
-
-            x.sort();
-
-            println!("{}-{}: {}+{} == {}",
-                     start_index,
-                     end_index,
-                     x[0],
-                     x[x.len()-1],
-                     x[0] + x[x.len()-1]);
-
-
-            return;
-        }
+fn part2(input: &[i64], n: i64) {
+    let ranges = ranges::contiguous_sums(input, n);
+    if ranges.is_empty() {
+        println!("Part 2: Couldn't find it!");
+    }
+    for r in ranges {
+        println!("{}-{}: {}+{} == {}", r.start, r.end, r.min, r.max, r.weakness());
+    }
+}
 
-        while sum > n && start_index < end_index{
-            sum -= input[start_index];
-            start_index += 1;
+// Validates each file, or stdin for `-` or when there are none, printing
+// every invalid number as `index value`, prefixed by the file when there's
+// more than one
+fn validate_streams(args: &[String]) {
+    let mut preamble = PREAMBLE;
+    let mut paths = vec![];
+    for arg in args {
+        match arg.strip_prefix("--preamble=") {
+            Some(n) => match n.parse::<usize>() {
+                Ok(n) if n >= 2 => preamble = n,
+                _ => {
+                    println!("Bad preamble length {:?}, expected a number of at least 2", n);
+                    return;
+                },
+            },
+            None => paths.push(arg.as_str()),
         }
+    }
+    if paths.is_empty() {
+        paths.push("-");
+    }
 
-        if end_index >= input.len() { break; }
-
-        while sum < n {
-            end_index += 1;
-            sum += input[end_index];
+    let prefixed = paths.len() > 1;
+    for path in paths {
+        let report = |invalid: validator::Invalid| {
+            let prefix = if prefixed { format!("{}: ", path) } else { String::new() };
+            println!("{}{} {}", prefix, invalid.index, invalid.value);
+        };
+        let result = if path == "-" {
+            validator::validate(std::io::stdin().lock(), preamble, report)
+        } else {
+            File::open(path)
+                .map_err(|e| e.to_string())
+                .and_then(|f| validator::validate(BufReader::new(f), preamble, report))
+        };
+        if let Err(e) = result {
+            eprintln!("{}: {}", path, e);
         }
     }
-
-    println!("Part 2: Couldn't find it!");
 }
 
 fn main() {
+    // `validate [--preamble=N] [FILE|-]...` streams files or stdin through the
+    // validator and reports every invalid number instead of solving
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(|a| a.as_str()) == Some("validate") {
+        validate_streams(&args[1..]);
+        return;
+    }
+
     println!("Reading input");
     let input = read_input();
-    let n = part1(&input);
-    part2(&input, n);
+    if let Some(n) = part1(&input) {
+        part2(&input, n);
+    }
 }
@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+// A contiguous run of the input, `start` and `end` both included
+#[derive(Debug, PartialEq, Eq)]
+pub struct SumRange {
+    pub start: usize,
+    pub end: usize,
+    pub min: i64,
+    pub max: i64,
+}
+
+impl SumRange {
+    // The encryption weakness: smallest plus largest number in the range
+    pub fn weakness(&self) -> i128 {
+        self.min as i128 + self.max as i128
+    }
+}
+
+// Every range of at least two numbers that adds up to `target`, sorted by
+// start then end. Works on negative numbers too: a range [s, e] sums to the
+// target exactly when prefix[e + 1] - prefix[s] does, so for each end it's a
+// lookup of the earlier prefix sums equal to prefix[e + 1] - target.
+pub fn contiguous_sums(input: &[i64], target: i64) -> Vec<SumRange> {
+    // Sums in i128 so long inputs of large numbers can't overflow
+    let mut prefix = vec![0i128];
+    for n in input {
+        prefix.push(prefix.last().unwrap() + *n as i128);
+    }
+
+    // Start indices by the prefix sum before them, only those at least two
+    // numbers before the current end
+    let mut starts: HashMap<i128, Vec<usize>> = HashMap::new();
+    let mut result = vec![];
+    for end in 1..input.len() {
+        starts.entry(prefix[end - 1]).or_default().push(end - 1);
+        for start in starts.get(&(prefix[end + 1] - target as i128)).into_iter().flatten() {
+            let range = &input[*start..=end];
+            result.push(SumRange {
+                start: *start,
+                end,
+                min: *range.iter().min().unwrap(),
+                max: *range.iter().max().unwrap(),
+            });
+        }
+    }
+
+    result.sort_by_key(|r| (r.start, r.end));
+    result
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::io::prelude::*;
+
+// Checks a stream of numbers one at a time against the `preamble` numbers
+// before each. The window is a multiset, so a value that appears more than
+// once stays in it until its last copy slides out.
+#[derive(Debug)]
+pub struct Validator {
+    preamble: usize,
+    window: VecDeque<i64>,
+    counts: HashMap<i64, usize>,
+}
+
+impl Validator {
+    pub fn new(preamble: usize) -> Validator {
+        Validator { preamble, window: VecDeque::new(), counts: HashMap::new() }
+    }
+
+    // Whether two different values in the window add up to `n`
+    fn is_sum_of_pair(&self, n: i64) -> bool {
+        self.counts.keys()
+            .any(|a| n.checked_sub(*a).is_some_and(|b| b != *a && self.counts.contains_key(&b)))
+    }
+
+    // Adds `n` to the window, returning false if it's invalid. The preamble
+    // itself is always valid.
+    pub fn push(&mut self, n: i64) -> bool {
+        let valid = self.window.len() < self.preamble || self.is_sum_of_pair(n);
+
+        self.window.push_back(n);
+        *self.counts.entry(n).or_insert(0) += 1;
+        if self.window.len() > self.preamble {
+            let old = self.window.pop_front().unwrap();
+            let count = self.counts.get_mut(&old).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(&old);
+            }
+        }
+
+        valid
+    }
+}
+
+// Indices are 0-based positions in the stream
+#[derive(Debug)]
+pub struct Invalid {
+    pub index: usize,
+    pub value: i64,
+}
+
+// Validates one number per line as it's read, calling `report` for every
+// invalid number. Blank lines are skipped. Stops at the first line that isn't
+// a number, with its 1-based line number.
+pub fn validate<R: BufRead>(reader: R, preamble: usize, mut report: impl FnMut(Invalid)) -> Result<(), String> {
+    let mut validator = Validator::new(preamble);
+    let mut index = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| format!("line {}: {}", i + 1, e))?;
+        if line.trim().is_empty() { continue; }
+
+        let value = line.trim().parse::<i64>()
+            .map_err(|_| format!("line {}: {:?} is not a number", i + 1, line))?;
+        if !validator.push(value) {
+            report(Invalid { index, value });
+        }
+        index += 1;
+    }
+    Ok(())
+}